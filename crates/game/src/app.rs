@@ -1,6 +1,10 @@
-use crate::state::State;
+use crate::{
+    state::State,
+    validation::{ValidationFailure, ValidationScope},
+};
 use std::{
     collections::HashSet,
+    fmt,
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -16,7 +20,9 @@ use winit::{
 pub struct Input {
     pub mouse_position: cgmath::Vector2<f32>,
     mouse_buttons: HashSet<MouseButton>,
+    mouse_buttons_just_pressed: HashSet<MouseButton>,
     keys: HashSet<KeyCode>,
+    keys_just_pressed: HashSet<KeyCode>,
 }
 
 impl Input {
@@ -24,9 +30,71 @@ impl Input {
         self.mouse_buttons.contains(&mouse_button)
     }
 
+    pub fn mouse_button_just_pressed(&self, mouse_button: MouseButton) -> bool {
+        self.mouse_buttons_just_pressed.contains(&mouse_button)
+    }
+
     pub fn key_pressed(&self, key: KeyCode) -> bool {
         self.keys.contains(&key)
     }
+
+    pub fn key_just_pressed(&self, key: KeyCode) -> bool {
+        self.keys_just_pressed.contains(&key)
+    }
+}
+
+pub struct AppConfig {
+    pub seed: u64,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self { seed: 42 }
+    }
+}
+
+pub const USAGE: &str = "usage: game [--seed <unsigned integer>]";
+
+#[derive(Debug)]
+pub enum ArgsError {
+    UnknownArgument(String),
+    MissingValue { arg: &'static str },
+    InvalidValue { arg: &'static str, value: String },
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgsError::UnknownArgument(arg) => write!(f, "unknown argument '{arg}'"),
+            ArgsError::MissingValue { arg } => write!(f, "{arg} should be followed by a value"),
+            ArgsError::InvalidValue { arg, value } => {
+                write!(f, "'{value}' is not a valid value for {arg}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ArgsError {}
+
+impl AppConfig {
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut config = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    let value = args
+                        .next()
+                        .ok_or(ArgsError::MissingValue { arg: "--seed" })?;
+                    config.seed = value.parse().map_err(|_| ArgsError::InvalidValue {
+                        arg: "--seed",
+                        value,
+                    })?;
+                }
+                _ => return Err(ArgsError::UnknownArgument(arg)),
+            }
+        }
+        Ok(config)
+    }
 }
 
 struct WindowState {
@@ -79,7 +147,7 @@ impl ApplicationHandler for App {
         } = window.inner_size();
         Self::recreate_surface(&self.device, &surface, &surface_config);
         self.state
-            .surface_resized(surface_config.width, surface_config.height);
+            .surface_resized(&self.device, surface_config.width, surface_config.height);
 
         self.window_state = Some(WindowState {
             window,
@@ -128,8 +196,11 @@ impl ApplicationHandler for App {
                     height: surface_config.height,
                 } = window.inner_size();
                 Self::recreate_surface(&self.device, surface, surface_config);
-                self.state
-                    .surface_resized(surface_config.width, surface_config.height);
+                self.state.surface_resized(
+                    &self.device,
+                    surface_config.width,
+                    surface_config.height,
+                );
 
                 self.render();
             }
@@ -139,7 +210,11 @@ impl ApplicationHandler for App {
                 state,
                 button,
             } => match state {
-                ElementState::Pressed => _ = self.input.mouse_buttons.insert(button),
+                ElementState::Pressed => {
+                    if self.input.mouse_buttons.insert(button) {
+                        self.input.mouse_buttons_just_pressed.insert(button);
+                    }
+                }
                 ElementState::Released => _ = self.input.mouse_buttons.remove(&button),
             },
 
@@ -173,7 +248,11 @@ impl ApplicationHandler for App {
                     },
                 is_synthetic: _,
             } => match state {
-                ElementState::Pressed => _ = self.input.keys.insert(key),
+                ElementState::Pressed => {
+                    if self.input.keys.insert(key) {
+                        self.input.keys_just_pressed.insert(key);
+                    }
+                }
                 ElementState::Released => _ = self.input.keys.remove(&key),
             },
 
@@ -182,7 +261,14 @@ impl ApplicationHandler for App {
     }
 
     fn about_to_wait(&mut self, #[expect(unused)] event_loop: &ActiveEventLoop) {
-        self.state.update(&self.input, self.dt.as_secs_f32());
+        self.state.update(
+            &self.device,
+            &self.queue,
+            &self.input,
+            self.dt.as_secs_f32(),
+        );
+        self.input.keys_just_pressed.clear();
+        self.input.mouse_buttons_just_pressed.clear();
         self.render();
     }
 }
@@ -222,8 +308,11 @@ impl App {
                     height: surface_config.height,
                 } = window.inner_size();
                 Self::recreate_surface(&self.device, surface, surface_config);
-                self.state
-                    .surface_resized(surface_config.width, surface_config.height);
+                self.state.surface_resized(
+                    &self.device,
+                    surface_config.width,
+                    surface_config.height,
+                );
                 return;
             }
             Err(wgpu::SurfaceError::Lost) => {
@@ -266,7 +355,12 @@ impl App {
             render_callback(&mut render_pass);
         }
 
-        self.queue.submit(std::iter::once(encoder.finish()));
+        {
+            // recording only fails once the commands are finished and submitted
+            let _validation_scope =
+                ValidationScope::new(&self.device, "Frame Submit", ValidationFailure::Panic);
+            self.queue.submit(std::iter::once(encoder.finish()));
+        }
 
         let suboptimal = surface_texture.suboptimal;
         surface_texture.present();
@@ -277,6 +371,14 @@ impl App {
 }
 
 pub(super) fn main() -> Result<(), winit::error::EventLoopError> {
+    let config = match AppConfig::from_args(std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("error: {error}\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
     let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
         backends: wgpu::Backends::all().with_env(),
         flags: wgpu::InstanceFlags::from_build_config().with_env(),
@@ -295,7 +397,8 @@ pub(super) fn main() -> Result<(), winit::error::EventLoopError> {
         let (device, queue) = adapter
             .request_device(&wgpu::DeviceDescriptor {
                 label: Some("Device"),
-                required_features: wgpu::Features::FLOAT32_FILTERABLE,
+                required_features: wgpu::Features::FLOAT32_FILTERABLE
+                    | (adapter.features() & wgpu::Features::POLYGON_MODE_LINE),
                 required_limits: adapter.limits(),
                 memory_hints: wgpu::MemoryHints::Performance,
                 trace: wgpu::Trace::Off,
@@ -306,7 +409,7 @@ pub(super) fn main() -> Result<(), winit::error::EventLoopError> {
         (device, queue)
     });
 
-    let state = State::new(&device, &queue);
+    let state = State::new(&device, &queue, &config);
 
     let mut app = App {
         last_time: None,
@@ -320,7 +423,9 @@ pub(super) fn main() -> Result<(), winit::error::EventLoopError> {
         input: Input {
             mouse_position: cgmath::vec2(0.0, 0.0),
             mouse_buttons: HashSet::new(),
+            mouse_buttons_just_pressed: HashSet::new(),
             keys: HashSet::new(),
+            keys_just_pressed: HashSet::new(),
         },
         window_state: None,
     };
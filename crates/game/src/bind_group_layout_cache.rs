@@ -0,0 +1,62 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Default)]
+pub struct BindGroupLayoutCache {
+    layouts: Mutex<HashMap<Vec<wgpu::BindGroupLayoutEntry>, Arc<wgpu::BindGroupLayout>>>,
+}
+
+impl BindGroupLayoutCache {
+    // layouts are keyed only by their entries, so the label of the first descriptor seen is kept
+    pub fn get_or_create(
+        &self,
+        device: &wgpu::Device,
+        descriptor: &wgpu::BindGroupLayoutDescriptor,
+    ) -> Arc<wgpu::BindGroupLayout> {
+        let mut layouts = self.layouts.lock().unwrap();
+        layouts
+            .entry(descriptor.entries.to_vec())
+            .or_insert_with(|| Arc::new(device.create_bind_group_layout(descriptor)))
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_entries_share_a_layout() {
+        let instance = wgpu::Instance::default();
+        let Ok(adapter) =
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+        else {
+            eprintln!("no adapter available, skipping");
+            return;
+        };
+        let (device, _queue) =
+            pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default()))
+                .expect("device should be created");
+
+        let descriptor = wgpu::BindGroupLayoutDescriptor {
+            label: Some("Test Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        };
+        let cache = BindGroupLayoutCache::default();
+        let first = cache.get_or_create(&device, &descriptor);
+        let second = cache.get_or_create(&device, &descriptor);
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(cache.layouts.lock().unwrap().len(), 1);
+    }
+}
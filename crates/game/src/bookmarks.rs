@@ -0,0 +1,29 @@
+use crate::camera::Camera;
+use std::path::Path;
+use winit::keyboard::KeyCode;
+
+pub type Bookmarks = [Option<Camera>; 10];
+
+pub const BOOKMARK_KEYS: [KeyCode; 10] = [
+    KeyCode::Digit0,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+pub fn save_to_file(bookmarks: &Bookmarks, path: &Path) -> std::io::Result<()> {
+    let file = std::fs::File::create(path)?;
+    serde_json::to_writer_pretty(std::io::BufWriter::new(file), bookmarks)?;
+    Ok(())
+}
+
+pub fn load_from_file(path: &Path) -> std::io::Result<Bookmarks> {
+    let file = std::fs::File::open(path)?;
+    Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+}
@@ -1,12 +1,40 @@
-use crate::Input;
-use math::{NoE2Rotor, Rotor, Transform};
-use std::f32::consts::TAU;
+use crate::{Input, ray_tracing::ProjectionMode};
+use cgmath::{InnerSpace, VectorSpace, Zero};
+use math::{Euler4, NoE2Rotor, Ray4, Rotor, Transform};
+use serde::{Deserialize, Serialize};
+use std::{
+    f32::consts::{FRAC_PI_2, TAU},
+    path::Path,
+};
 use winit::{event::MouseButton, keyboard::KeyCode};
 
+/// how far in front of the camera blocks are picked
+pub const PICK_DISTANCE: f32 = 5.0;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Camera {
     pub position: cgmath::Vector4<f32>,
     pub rotation: NoE2Rotor,
     pub xy_rotation: f32,
+    /// rotations in planes containing y, applied after `rotation` and `xy_rotation` so those stay upright
+    #[serde(default = "Rotor::identity")]
+    pub tilt: Rotor,
+    pub ground_movement: bool,
+    #[serde(default)]
+    pub orbit_target: Option<cgmath::Vector4<f32>>,
+    #[serde(default = "default_fov")]
+    pub fov_radians: f32,
+    #[serde(default)]
+    pub orthographic: bool,
+    #[serde(default = "default_orthographic_half_width")]
+    pub orthographic_half_width: f32,
+    #[serde(skip, default = "cgmath::Vector4::zero")]
+    pub velocity: cgmath::Vector4<f32>,
+    #[serde(default = "default_damping")]
+    pub damping: f32,
+    #[serde(default = "default_acceleration")]
+    pub acceleration: f32,
+    speed: f32,
 }
 
 impl Default for Camera {
@@ -15,49 +43,230 @@ impl Default for Camera {
             position: cgmath::vec4(0.0, 0.0, 0.0, 0.0),
             rotation: NoE2Rotor::identity(),
             xy_rotation: 0.0,
+            tilt: Rotor::identity(),
+            ground_movement: true,
+            orbit_target: None,
+            fov_radians: default_fov(),
+            orthographic: false,
+            orthographic_half_width: default_orthographic_half_width(),
+            velocity: cgmath::Vector4::zero(),
+            damping: default_damping(),
+            acceleration: default_acceleration(),
+            speed: 10.0,
         }
     }
 }
 
 impl Camera {
+    pub fn lerp(a: &Camera, b: &Camera, t: f32) -> Camera {
+        if t <= 0.0 {
+            return *a;
+        }
+        if t >= 1.0 {
+            return *b;
+        }
+
+        Camera {
+            position: a.position.lerp(b.position, t),
+            rotation: a.rotation.slerp(b.rotation, t),
+            xy_rotation: a.xy_rotation + (b.xy_rotation - a.xy_rotation) * t,
+            tilt: a.tilt.slerp(b.tilt, t),
+            ..*a
+        }
+    }
+
+    pub fn is_near(&self, other: &Camera, epsilon: f32) -> bool {
+        let (a, b) = (self.rotor(), other.rotor());
+        (self.position - other.position).magnitude() < epsilon
+            && (a.x() - b.x()).magnitude() < epsilon
+            && (a.y() - b.y()).magnitude() < epsilon
+            && (a.z() - b.z()).magnitude() < epsilon
+            && (a.w() - b.w()).magnitude() < epsilon
+    }
+
+    pub fn save_to_file(&self, path: &Path) -> std::io::Result<()> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    pub fn load_from_file(path: &Path) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    pub fn rotor(&self) -> Rotor {
+        Rotor::from_no_e2_rotor(self.rotation)
+            .then(Rotor::rotate_xy(self.xy_rotation))
+            .then(self.tilt)
+    }
+
+    /// the orientation as an angle in each plane, for showing to people
+    pub fn euler_angles(&self) -> Euler4 {
+        self.rotor().euler_angles()
+    }
+
     pub fn transform(&self) -> Transform {
-        Transform::translation(self.position).then(Transform::from_rotor(
-            Rotor::from_no_e2_rotor(self.rotation).then(Rotor::rotate_xy(self.xy_rotation)),
-        ))
+        Transform::translation(self.position).then(Transform::from_rotor(self.rotor()))
+    }
+
+    pub fn reset(&mut self) {
+        self.position = cgmath::vec4(0.0, 0.0, 0.0, 0.0);
+        self.rotation = NoE2Rotor::identity();
+        self.xy_rotation = 0.0;
+        self.tilt = Rotor::identity();
+        self.velocity = cgmath::Vector4::zero();
+    }
+
+    pub fn set_fov(&mut self, fov_radians: f32) {
+        self.fov_radians = fov_radians.clamp(10f32.to_radians(), 170f32.to_radians());
+    }
+
+    pub fn projection(&self) -> ProjectionMode {
+        if self.orthographic {
+            ProjectionMode::Orthographic {
+                half_width: self.orthographic_half_width,
+            }
+        } else {
+            ProjectionMode::Perspective {
+                fov: self.fov_radians,
+            }
+        }
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.clamp(0.1, 100.0);
+    }
+
+    /// the position padded to a fixed width, so the numbers don't move as they change
+    pub fn position_string(&self) -> String {
+        let cgmath::Vector4 { x, y, z, w } = self.position;
+        format!("(x={x:8.2}, y={y:8.2}, z={z:8.2}, w={w:8.2})")
+    }
+
+    /// the direction the camera is looking with the y component removed, when looking straight
+    /// up or down this is the forward direction of `rotation`, which is always level
+    pub fn forward_flat(&self) -> cgmath::Vector4<f32> {
+        let mut forward = self.rotor().x();
+        forward.y = 0.0;
+        if forward.magnitude2() < 1e-6 {
+            return self.rotation.x();
+        }
+        forward.normalize()
+    }
+
+    /// the ray from the camera along its forward direction
+    pub fn ray(&self) -> Ray4 {
+        Ray4::new(self.position, self.rotor().x())
+    }
+
+    /// the block a fixed distance in front of the camera, where new blocks are placed
+    pub fn looked_at_block(&self) -> cgmath::Vector4<f32> {
+        let point = self.ray().at(PICK_DISTANCE);
+        point.map(|c| c.floor() + 0.5)
     }
 
     pub fn update(&mut self, input: &Input, ts: f32) {
-        let speed = 10.0;
+        if input.key_just_pressed(KeyCode::Home) {
+            self.reset();
+        }
 
-        let forward = self.rotation.x();
-        let up = self.rotation.y();
-        let right = self.rotation.z();
-        let ana = self.rotation.w();
+        if input.key_just_pressed(KeyCode::Equal) {
+            self.set_speed(self.speed * 1.25);
+        }
+        if input.key_just_pressed(KeyCode::Minus) {
+            self.set_speed(self.speed / 1.25);
+        }
+        let speed = self.speed;
+
+        if input.key_just_pressed(KeyCode::KeyO) {
+            self.orthographic = !self.orthographic;
+        }
+
+        if input.key_just_pressed(KeyCode::NumpadAdd) {
+            self.set_fov(self.fov_radians + 5f32.to_radians());
+        }
+        if input.key_just_pressed(KeyCode::NumpadSubtract) {
+            self.set_fov(self.fov_radians - 5f32.to_radians());
+        }
 
+        if input.key_just_pressed(KeyCode::Escape) {
+            self.orbit_target = None;
+        }
+
+        if input.key_just_pressed(KeyCode::KeyC) {
+            self.ground_movement = !self.ground_movement;
+        }
+
+        // `rotation` and `xy_rotation` can't hold rotations in planes containing y without
+        // tipping the camera over, so these go in `tilt` and mouse look stays level
+        let rotation_speed = 1.5;
+        if input.key_pressed(KeyCode::BracketLeft) {
+            self.tilt = self.tilt.then(Rotor::rotate_yw(-rotation_speed * ts));
+        }
+        if input.key_pressed(KeyCode::BracketRight) {
+            self.tilt = self.tilt.then(Rotor::rotate_yw(rotation_speed * ts));
+        }
+        if input.key_pressed(KeyCode::Semicolon) {
+            self.tilt = self.tilt.then(Rotor::rotate_yz(-rotation_speed * ts));
+        }
+        if input.key_pressed(KeyCode::Quote) {
+            self.tilt = self.tilt.then(Rotor::rotate_yz(rotation_speed * ts));
+        }
+
+        // walking stays level, flying moves along the axes of the full camera rotation
+        let (forward, up, right, ana) = if self.ground_movement {
+            (
+                self.forward_flat(),
+                self.rotation.y(),
+                self.rotation.z(),
+                self.rotation.w(),
+            )
+        } else {
+            let rotor = self.rotor();
+            (rotor.x(), rotor.y(), rotor.z(), rotor.w())
+        };
+
+        let mut direction = cgmath::Vector4::zero();
         if input.key_pressed(KeyCode::KeyW) {
-            self.position += forward * speed * ts;
+            direction += forward;
         }
         if input.key_pressed(KeyCode::KeyS) {
-            self.position -= forward * speed * ts;
+            direction -= forward;
         }
         if input.key_pressed(KeyCode::KeyA) {
-            self.position -= right * speed * ts;
+            direction -= right;
         }
         if input.key_pressed(KeyCode::KeyD) {
-            self.position += right * speed * ts;
+            direction += right;
         }
         if input.key_pressed(KeyCode::KeyQ) {
-            self.position -= up * speed * ts;
+            direction -= up;
         }
         if input.key_pressed(KeyCode::KeyE) {
-            self.position += up * speed * ts;
+            direction += up;
         }
         if input.key_pressed(KeyCode::KeyR) {
-            self.position += ana * speed * ts;
+            direction += ana;
         }
         if input.key_pressed(KeyCode::KeyF) {
-            self.position -= ana * speed * ts;
+            direction -= ana;
+        }
+
+        // moving accelerates up to `speed` in any direction, drag only applies once no keys are held
+        if direction.magnitude2() > 0.0 {
+            self.velocity += direction.normalize() * speed * self.acceleration * ts;
+            if self.velocity.magnitude2() > speed * speed {
+                self.velocity = self.velocity.normalize_to(speed);
+            }
+        } else {
+            self.velocity *= (1.0 - self.damping * ts).max(0.0);
         }
+        self.position += self.velocity * ts;
     }
 
     pub fn mouse_moved(&mut self, input: &Input, delta: cgmath::Vector2<f32>) {
@@ -72,10 +281,83 @@ impl Camera {
         }
 
         if input.mouse_button_pressed(MouseButton::Right) {
+            // the target is kept at the same position relative to the camera
+            let local_target = self.orbit_target.map(|target| {
+                self.rotor()
+                    .reverse()
+                    .transform_direction(target - self.position)
+            });
+
             self.rotation = self
                 .rotation
                 .then(NoE2Rotor::rotate_zw(delta.x * sensitivity))
                 .then(NoE2Rotor::rotate_xw(delta.y * sensitivity));
+
+            if let (Some(target), Some(local_target)) = (self.orbit_target, local_target) {
+                self.position = target - self.rotor().transform_direction(local_target);
+            }
         }
     }
 }
+
+fn default_fov() -> f32 {
+    FRAC_PI_2
+}
+
+fn default_damping() -> f32 {
+    8.0
+}
+
+fn default_acceleration() -> f32 {
+    10.0
+}
+
+fn default_orthographic_half_width() -> f32 {
+    16.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reset_returns_to_identity() {
+        let mut camera = Camera {
+            position: cgmath::vec4(1.0, -2.0, 3.0, 4.5),
+            rotation: NoE2Rotor::rotate_xz(0.8).then(NoE2Rotor::rotate_zw(-1.1)),
+            xy_rotation: 0.4,
+            tilt: Rotor::rotate_yw(0.3),
+            ..Camera::default()
+        };
+        camera.reset();
+
+        let (transform, identity) = (camera.transform(), Transform::identity());
+        for (a, b) in [
+            (transform.position(), identity.position()),
+            (transform.x(), identity.x()),
+            (transform.y(), identity.y()),
+            (transform.z(), identity.z()),
+            (transform.w(), identity.w()),
+        ] {
+            assert!((a - b).magnitude() < 1e-5, "{a:?} != {b:?}");
+        }
+    }
+
+    #[test]
+    fn forward_flat_is_level() {
+        let mut camera = Camera {
+            rotation: NoE2Rotor::rotate_xz(0.8).then(NoE2Rotor::rotate_xw(-0.5)),
+            xy_rotation: 0.6,
+            ..Camera::default()
+        };
+        let forward = camera.forward_flat();
+        assert!(forward.y.abs() < 1e-5, "{forward:?} is not level");
+        assert!((forward.magnitude() - 1.0).abs() < 1e-5);
+        assert!(forward.dot(camera.rotor().x()) > 0.0);
+
+        // looking straight up leaves nothing to project, so the level forward direction is used
+        camera.xy_rotation = TAU * 0.25;
+        let forward = camera.forward_flat();
+        assert!((forward - camera.rotation.x()).magnitude() < 1e-5);
+    }
+}
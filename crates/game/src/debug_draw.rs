@@ -0,0 +1,84 @@
+use crate::{
+    ray_tracing::CameraBasis,
+    ui::{Line, LineWidth, Quad, Ui},
+};
+
+// the frustum is drawn in camera space from the outside, in an inset in the bottom left corner,
+// as drawing it from the camera's own point of view would only show its outline
+pub fn draw_frustum(
+    ui: &mut Ui,
+    basis: CameraBasis,
+    fov: f32,
+    aspect: f32,
+    near: f32,
+    far: f32,
+    color: cgmath::Vector3<f32>,
+) {
+    let inset_size = 0.6;
+    let inset_position = cgmath::vec2(-aspect + inset_size * 0.5, -1.0 + inset_size * 0.5);
+    ui.push_quad(
+        Quad {
+            position: inset_position,
+            size: cgmath::vec2(inset_size, inset_size),
+            color: cgmath::vec4(0.0, 0.0, 0.0, 0.6),
+            gradient: None,
+            pulse_phase: None,
+        },
+        None,
+    );
+
+    // an oblique projection where each of the camera space axes gets its own screen direction
+    let axis_directions = [
+        cgmath::vec2(1.0, 0.0),
+        cgmath::vec2(0.0, 1.0),
+        cgmath::vec2(0.45, 0.3),
+        cgmath::vec2(-0.35, 0.3),
+    ];
+    let project = |point: cgmath::Vector4<f32>| {
+        let scale = inset_size * 0.4 / far;
+        let origin = inset_position - cgmath::vec2(inset_size * 0.2, inset_size * 0.1);
+        origin
+            + (axis_directions[0] * point.x
+                + axis_directions[1] * point.y
+                + axis_directions[2] * point.z
+                + axis_directions[3] * point.w)
+                * scale
+    };
+
+    let [forward_axis, up_axis, right_axis] = basis.axes();
+    let half_height = (fov * 0.5).tan();
+    let half_width = half_height * aspect;
+    let corner = |distance: f32, u: f32, v: f32| {
+        let mut point = cgmath::vec4(0.0, 0.0, 0.0, 0.0);
+        point[forward_axis] = distance;
+        point[right_axis] = u * half_width * distance;
+        point[up_axis] = v * half_height * distance;
+        point
+    };
+
+    let mut edges = vec![];
+    for distance in [near, far] {
+        let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+            .map(|(u, v)| corner(distance, u, v));
+        for i in 0..corners.len() {
+            edges.push((corners[i], corners[(i + 1) % corners.len()]));
+        }
+    }
+    for (u, v) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+        edges.push((corner(near, u, v), corner(far, u, v)));
+    }
+
+    for (a, b) in edges {
+        let along_w = (b.w - a.w).abs() > f32::EPSILON;
+        ui.push_line(Line {
+            a: project(a),
+            b: project(b),
+            color: if along_w {
+                cgmath::vec3(0.7, 0.2, 1.0)
+            } else {
+                color
+            },
+            width: LineWidth::ScreenPixels(2.0),
+        });
+    }
+}
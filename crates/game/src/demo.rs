@@ -0,0 +1,112 @@
+use crate::{
+    camera::Camera,
+    ui::{Font, Line, LineWidth, Quad, Ui},
+};
+use math::{Plane4, Rotor};
+
+// the planes the hypercube spins in, and how fast in radians per second
+const SPIN: [(Plane4, f32); 3] = [(Plane4::XW, 0.5), (Plane4::YZ, 0.3), (Plane4::ZW, 0.2)];
+
+pub struct HypercubeDemo {
+    pub enabled: bool,
+    time: f32,
+}
+
+impl Default for HypercubeDemo {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            time: 0.0,
+        }
+    }
+}
+
+impl HypercubeDemo {
+    pub fn update(&mut self, ts: f32) {
+        if self.enabled {
+            self.time += ts;
+        }
+    }
+
+    pub fn draw(&self, ui: &mut Ui, font: &Font, camera: &Camera, aspect: f32) {
+        if !self.enabled {
+            return;
+        }
+
+        ui.push_quad(
+            Quad {
+                position: cgmath::vec2(0.0, 0.0),
+                size: cgmath::vec2(2.0 * aspect, 2.0),
+                color: cgmath::vec4(0.0, 0.0, 0.0, 0.8),
+                gradient: None,
+                pulse_phase: None,
+            },
+            None,
+        );
+
+        let rotation = SPIN
+            .iter()
+            .fold(Rotor::identity(), |rotation, &(plane, speed)| {
+                rotation.then(Rotor::from_plane_angle(plane, self.time * speed))
+            })
+            .then(camera.rotor().reverse());
+
+        let vertices: [cgmath::Vector2<f32>; 16] = std::array::from_fn(|i| {
+            let corner = cgmath::vec4(
+                if i & 1 == 0 { -0.5 } else { 0.5 },
+                if i & 2 == 0 { -0.5 } else { 0.5 },
+                if i & 4 == 0 { -0.5 } else { 0.5 },
+                if i & 8 == 0 { -0.5 } else { 0.5 },
+            );
+            let v = rotation.transform_direction(corner);
+
+            // project along w into 3d, then along the forward (x) axis onto the screen
+            let w_scale = 2.0 / (3.0 - v.w);
+            let projected = v.truncate() * w_scale;
+            let x_scale = 2.0 / (4.0 + projected.x);
+            cgmath::vec2(projected.z, projected.y) * x_scale * 0.6
+        });
+
+        let axis_colors = [
+            cgmath::vec3(1.0, 0.2, 0.2),
+            cgmath::vec3(0.2, 1.0, 0.2),
+            cgmath::vec3(0.2, 0.4, 1.0),
+            cgmath::vec3(1.0, 0.2, 1.0),
+        ];
+        // every pair of corners that differ along exactly one axis is an edge
+        for (i, &a) in vertices.iter().enumerate() {
+            for (axis, &color) in axis_colors.iter().enumerate() {
+                let j = i | (1 << axis);
+                if j == i {
+                    continue;
+                }
+                ui.push_line(Line {
+                    a,
+                    b: vertices[j],
+                    color,
+                    width: LineWidth::ScreenPixels(2.0),
+                });
+            }
+        }
+
+        let text_color = cgmath::vec4(1.0, 1.0, 1.0, 1.0);
+        let text_x = -aspect + 0.6;
+        let mut text_y = 0.7;
+        for line in [
+            "Rotation planes:",
+            "XY - forward towards up",
+            "XZ - forward towards right",
+            "XW - forward towards ana",
+            "YZ - up towards right",
+            "YW - up towards ana",
+            "ZW - right towards ana",
+            "",
+            "Left drag: XZ and XY",
+            "Right drag: ZW and XW",
+            "T: close tutorial",
+        ] {
+            font.draw_str(ui, line, cgmath::vec2(text_x, text_y), 0.06, text_color);
+            text_y -= 0.08;
+        }
+    }
+}
@@ -3,8 +3,15 @@ pub mod state;
 pub mod ui;
 pub mod camera;
 pub mod ray_tracing;
+pub mod validation;
+pub mod world_generator;
+pub mod bind_group_layout_cache;
+pub mod demo;
+pub mod debug_draw;
+pub mod sun;
+pub mod bookmarks;
 
-pub use app::Input;
+pub use app::{AppConfig, Input};
 
 fn main() -> Result<(), winit::error::EventLoopError> {
     app::main()
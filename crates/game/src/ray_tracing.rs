@@ -1,64 +1,184 @@
-use math::Transform;
+use crate::{
+    bind_group_layout_cache::BindGroupLayoutCache,
+    validation::{ValidationFailure, ValidationScope},
+    world_generator::{self, ProceduralSettings, WorldGenerator},
+};
+use bytemuck::{Pod, Zeroable};
+use cgmath::{Array, InnerSpace};
+use chunk::ChunkShared;
+use math::{Aabb4, Transform};
+use std::{collections::HashMap, io, path::Path, sync::Arc};
 
+pub mod block_colors;
+pub mod block_registry;
+pub mod chunk;
+pub mod chunk_file;
+pub mod rle_decoder;
 pub mod target;
 
-pub use target::*;
+pub use {
+    block_colors::BlockColorTexture,
+    block_registry::{BlockRegistry, BlockType},
+    chunk::ChunkData,
+    rle_decoder::RleDecoder,
+    target::*,
+};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CameraBasis {
     XYZ,
     XYW,
     XWZ,
 }
 
+impl CameraBasis {
+    pub fn axes(self) -> [usize; 3] {
+        match self {
+            CameraBasis::XYZ => [0, 1, 2],
+            CameraBasis::XYW => [0, 1, 3],
+            CameraBasis::XWZ => [0, 3, 2],
+        }
+    }
+
+    /// the axis the basis doesn't span, which is hidden from the view so the compass sorts by it,
+    /// this is 3 (w) for `XYZ` and 2 (z) for `XYW`, not the third axis of the basis
+    pub fn depth_axis(self) -> usize {
+        let axes = self.axes();
+        (0..4)
+            .find(|axis| !axes.contains(axis))
+            .expect("a basis should only span 3 of the 4 axes")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProjectionMode {
+    Perspective { fov: f32 },
+    Orthographic { half_width: f32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sky {
+    Color(cgmath::Vector4<f32>),
+    /// blended by how much the ray points along the camera's up direction
+    Gradient {
+        top: cgmath::Vector4<f32>,
+        bottom: cgmath::Vector4<f32>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AmbientOcclusion {
+    pub enabled: bool,
+    /// how far from a hit blocks are looked for
+    pub radius: f32,
+    /// how much a hit surrounded by blocks is darkened, from 0 to 1
+    pub strength: f32,
+}
+
+impl Default for AmbientOcclusion {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            radius: 1.5,
+            strength: 0.5,
+        }
+    }
+}
+
+/// a spherical light that casts soft shadows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light {
+    pub position: cgmath::Vector4<f32>,
+    pub radius: f32,
+    pub color: cgmath::Vector3<f32>,
+}
+
+// how much of each new frame is blended over the previous ones while the camera is still
+const ACCUMULATION_WEIGHT: f32 = 0.05;
+
 pub struct RayTracing {
-    chunk_bind_group: wgpu::BindGroup,
+    chunk_size: usize,
+    chunk_shared: ChunkShared,
+    chunks: HashMap<String, ChunkData>,
+    active_chunk: String,
+    block_colors: BlockColorTexture,
+    block_registry: BlockRegistry,
+    projection: ProjectionMode,
+    sky: Sky,
+    ambient_occlusion: AmbientOcclusion,
+    debug_normals: bool,
+    light: Option<Light>,
+    shadow_samples: u32,
+    max_steps: u32,
+    w_slice: Option<f32>,
+    show_grid: bool,
+    grid_color: cgmath::Vector4<f32>,
+    show_chunk_bounds: bool,
+    /// the name of the chunk being generated, and the thread generating it
+    procedural_generation: Option<(String, std::thread::JoinHandle<Vec<[u32; 2]>>)>,
+
+    accumulated: bool,
+    /// holds the previous frame, it should be the same size as the target being rendered to
+    accumulation_target: RayTracingTarget,
+    accumulation_bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    accumulation_bind_group: wgpu::BindGroup,
+    accumulation_weight: f32,
+    /// the camera of the previous frame without its jitter, to know when it moves
+    last_camera: Option<GpuCamera>,
 
     ray_tracing_pipeline: wgpu::ComputePipeline,
+    downsample_pipeline: wgpu::ComputePipeline,
+    chunk_bounds_pipeline: wgpu::ComputePipeline,
 }
 
 impl RayTracing {
-    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
-        let target_bind_group_layout = target::bind_group_layout(device);
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bind_group_layout_cache: &BindGroupLayoutCache,
+        world_generator: &mut WorldGenerator,
+    ) -> Self {
+        let target_bind_group_layout = target::bind_group_layout(device, bind_group_layout_cache);
 
         let chunk_size = 128usize;
 
-        let chunk_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Chunk Buffer"),
-            size: (chunk_size.pow(4) * size_of::<u32>()) as _,
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        let lighting_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Lighting Uniform Buffer"),
+            size: size_of::<GpuLighting>().next_multiple_of(16) as _,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
-        let chunk_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Chunk Bind Group Layout"),
+        let chunk_shared = ChunkShared::new(device, bind_group_layout_cache, lighting_buffer);
+
+        let block_colors_bind_group_layout =
+            block_colors::bind_group_layout(device, bind_group_layout_cache);
+        let block_registry = BlockRegistry::default();
+        let block_colors =
+            BlockColorTexture::new(device, queue, bind_group_layout_cache, &block_registry);
+
+        let accumulation_bind_group_layout = bind_group_layout_cache.get_or_create(
+            device,
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Accumulation Bind Group Layout"),
                 entries: &[wgpu::BindGroupLayoutEntry {
                     binding: 0,
                     visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage { read_only: true },
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
                     },
                     count: None,
                 }],
-            });
-        let chunk_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Chunk Bind Group"),
-            layout: &chunk_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: chunk_buffer.as_entire_binding(),
-            }],
-        });
-
-        queue.write_buffer(
-            &chunk_buffer,
-            0,
-            bytemuck::cast_slice(
-                &std::iter::repeat_with(|| rand::random_range(0.0..=1.0) > 0.99)
-                    .take(chunk_size.pow(4))
-                    .collect::<Vec<_>>(),
-            ),
+            },
+        );
+        let accumulation_target = RayTracingTargetBuilder::new(device, bind_group_layout_cache)
+            .name("Accumulation Texture")
+            .build();
+        let accumulation_bind_group = accumulation_bind_group(
+            device,
+            &accumulation_bind_group_layout,
+            &accumulation_target,
         );
 
         let ray_tracing_shader = device.create_shader_module(wgpu::include_wgsl!(concat!(
@@ -68,7 +188,12 @@ impl RayTracing {
         let ray_tracing_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Ray Tracing Pipeline Layout"),
-                bind_group_layouts: &[&target_bind_group_layout, &chunk_bind_group_layout],
+                bind_group_layouts: &[
+                    &target_bind_group_layout,
+                    &chunk_shared.bind_group_layout,
+                    &block_colors_bind_group_layout,
+                    &accumulation_bind_group_layout,
+                ],
                 push_constant_ranges: &[],
             });
         let ray_tracing_pipeline =
@@ -81,22 +206,435 @@ impl RayTracing {
                 cache: None,
             });
 
-        Self {
-            chunk_bind_group,
+        let downsample_bind_group_layout =
+            target::downsample_bind_group_layout(device, bind_group_layout_cache);
+        let downsample_shader = device.create_shader_module(wgpu::include_wgsl!(concat!(
+            env!("OUT_DIR"),
+            "/shaders/downsample.wgsl"
+        )));
+        let downsample_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Downsample Pipeline Layout"),
+                bind_group_layouts: &[&downsample_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let downsample_pipeline =
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Downsample Pipeline"),
+                layout: Some(&downsample_pipeline_layout),
+                module: &downsample_shader,
+                entry_point: Some("downsample"),
+                compilation_options: Default::default(),
+                cache: None,
+            });
+
+        let chunk_bounds_shader = device.create_shader_module(wgpu::include_wgsl!(concat!(
+            env!("OUT_DIR"),
+            "/shaders/chunk_bounds.wgsl"
+        )));
+        let chunk_bounds_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Chunk Bounds Pipeline Layout"),
+                bind_group_layouts: &[&target_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let chunk_bounds_pipeline =
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Chunk Bounds Pipeline"),
+                layout: Some(&chunk_bounds_pipeline_layout),
+                module: &chunk_bounds_shader,
+                entry_point: Some("draw_chunk_bounds"),
+                compilation_options: Default::default(),
+                cache: None,
+            });
+
+        let mut ray_tracing = Self {
+            chunk_size,
+            chunk_shared,
+            chunks: HashMap::new(),
+            active_chunk: "overworld".into(),
+            block_colors,
+            block_registry,
+            projection: ProjectionMode::Perspective {
+                fov: std::f32::consts::FRAC_PI_2,
+            },
+            sky: Sky::Gradient {
+                top: cgmath::vec4(0.4, 0.5, 0.8, 1.0),
+                bottom: cgmath::vec4(0.1, 0.2, 0.4, 1.0),
+            },
+            ambient_occlusion: AmbientOcclusion::default(),
+            debug_normals: false,
+            light: None,
+            shadow_samples: 8,
+            max_steps: 256,
+            w_slice: None,
+            show_grid: false,
+            grid_color: cgmath::vec4(0.0, 0.0, 0.0, 0.5),
+            show_chunk_bounds: false,
+            procedural_generation: None,
+
+            accumulated: true,
+            accumulation_target,
+            accumulation_bind_group_layout,
+            accumulation_bind_group,
+            accumulation_weight: 1.0,
+            last_camera: None,
 
             ray_tracing_pipeline,
+            downsample_pipeline,
+            chunk_bounds_pipeline,
+        };
+        ray_tracing.add_chunk(device, queue, "overworld");
+        ray_tracing.regenerate(device, queue, world_generator);
+        ray_tracing.set_lighting(queue, cgmath::vec4(-0.3, 1.0, 0.4, 0.1));
+        ray_tracing
+    }
+
+    /// adds an empty chunk, replacing any chunk that already has the name
+    pub fn add_chunk(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, name: &str) {
+        let _validation_scope =
+            ValidationScope::new(device, "Chunk Creation", ValidationFailure::Panic);
+        let chunk = ChunkData::new(device, queue, &self.chunk_shared, self.chunk_size);
+        self.chunks.insert(name.into(), chunk);
+    }
+
+    pub fn remove_chunk(&mut self, name: &str) -> Option<ChunkData> {
+        assert_ne!(
+            name, self.active_chunk,
+            "the active chunk should not be removed"
+        );
+        self.chunks.remove(name)
+    }
+
+    pub fn has_chunk(&self, name: &str) -> bool {
+        self.chunks.contains_key(name)
+    }
+
+    /// the chunk that is rendered and that block edits go to
+    pub fn active_chunk(&self) -> &str {
+        &self.active_chunk
+    }
+
+    pub fn set_active_chunk(&mut self, name: &str) {
+        assert!(
+            self.chunks.contains_key(name),
+            "the active chunk should have been added"
+        );
+        self.active_chunk = name.into();
+        self.reset_accumulation();
+    }
+
+    fn chunk(&self) -> &ChunkData {
+        &self.chunks[&self.active_chunk]
+    }
+
+    fn chunk_mut(&mut self) -> &mut ChunkData {
+        self.chunks
+            .get_mut(&self.active_chunk)
+            .expect("the active chunk should always exist")
+    }
+
+    pub fn regenerate(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        world_generator: &mut WorldGenerator,
+    ) {
+        let _validation_scope =
+            ValidationScope::new(device, "Chunk Upload", ValidationFailure::Panic);
+
+        let slice_length = self.chunk_size.pow(3);
+        let chunk = self
+            .chunks
+            .get_mut(&self.active_chunk)
+            .expect("the active chunk should always exist");
+        chunk
+            .upload_slices(device, queue, &mut self.chunk_shared, |blocks| {
+                blocks.copy_from_slice(&world_generator.generate_blocks(slice_length));
+                Ok(())
+            })
+            .expect("generating blocks should not fail");
+        self.reset_accumulation();
+    }
+
+    pub fn load_chunk(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &Path,
+    ) -> io::Result<()> {
+        let chunk = self
+            .chunks
+            .get_mut(&self.active_chunk)
+            .expect("the active chunk should always exist");
+        let result = chunk.load(device, queue, &mut self.chunk_shared, path);
+        self.reset_accumulation();
+        result
+    }
+
+    pub fn save_chunk(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &Path,
+    ) -> io::Result<()> {
+        self.chunk().save(device, queue, path)
+    }
+
+    /// starts generating the active chunk from noise on a background thread,
+    /// it replaces that chunk once `finish_procedural` sees that it is done
+    pub fn fill_procedural(&mut self, settings: ProceduralSettings) {
+        let chunk_size = self.chunk_size;
+        let stone = self
+            .block_registry
+            .find("stone")
+            .expect("the default blocks should include stone")
+            .id;
+        let block = pack_block(stone as u8, 255, 255, 255);
+        let thread = std::thread::spawn(move || {
+            world_generator::generate_procedural(settings, chunk_size, block)
+        });
+        self.procedural_generation = Some((self.active_chunk.clone(), thread));
+    }
+
+    pub fn is_generating(&self) -> bool {
+        self.procedural_generation.is_some()
+    }
+
+    /// uploads the procedurally generated chunk if it has finished, returns whether it did
+    pub fn finish_procedural(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> bool {
+        if !self
+            .procedural_generation
+            .as_ref()
+            .is_some_and(|(_, thread)| thread.is_finished())
+        {
+            return false;
+        }
+        let (name, thread) = self
+            .procedural_generation
+            .take()
+            .expect("the generation thread was just checked");
+        let filled = thread
+            .join()
+            .expect("the procedural generation thread should not panic");
+
+        // the chunk may have been removed while it was generating
+        if let Some(chunk) = self.chunks.get_mut(&name) {
+            let _validation_scope =
+                ValidationScope::new(device, "Chunk Upload", ValidationFailure::Panic);
+            chunk.upload_filled(device, queue, &mut self.chunk_shared, filled);
+            self.reset_accumulation();
         }
+        true
     }
 
-    pub fn render(
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// the box every block of a chunk is inside of
+    pub fn chunk_bounds(&self) -> Aabb4 {
+        Aabb4::new(
+            cgmath::vec4(0.0, 0.0, 0.0, 0.0),
+            cgmath::Vector4::from_value(self.chunk_size as f32),
+        )
+    }
+
+    /// the edit is uploaded on the next `flush_blocks`, `block` should come from `pack_block`
+    pub fn set_block(&mut self, position: cgmath::Vector4<u32>, block: u32) {
+        self.chunk_mut().set_block(position, block);
+    }
+
+    pub fn get_blocks(
         &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        positions: &[cgmath::Vector4<u32>],
+    ) -> Vec<u32> {
+        self.chunk().get_blocks(device, queue, positions)
+    }
+
+    pub fn flush_blocks(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.chunk().has_edits() {
+            self.reset_accumulation();
+        }
+        for chunk in self.chunks.values_mut() {
+            chunk.flush_blocks(device, queue, &mut self.chunk_shared);
+        }
+    }
+
+    pub fn accumulated(&self) -> bool {
+        self.accumulated
+    }
+
+    /// blends consecutive frames together while the camera is still, which smooths out the noise
+    pub fn set_accumulated(&mut self, accumulated: bool) {
+        self.accumulated = accumulated;
+        self.reset_accumulation();
+    }
+
+    /// makes the next frame ignore the previous ones
+    pub fn reset_accumulation(&mut self) {
+        self.accumulation_weight = 1.0;
+    }
+
+    fn resize_accumulation_target(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.accumulation_target.resize(device, width, height);
+        self.accumulation_bind_group = accumulation_bind_group(
+            device,
+            &self.accumulation_bind_group_layout,
+            &self.accumulation_target,
+        );
+        self.reset_accumulation();
+    }
+
+    pub fn set_lighting(&self, queue: &wgpu::Queue, sun_direction: cgmath::Vector4<f32>) {
+        let lighting = GpuLighting {
+            sun_direction: sun_direction.normalize().into(),
+        };
+        queue.write_buffer(
+            &self.chunk_shared.lighting_buffer,
+            0,
+            bytemuck::bytes_of(&lighting),
+        );
+    }
+
+    pub fn projection(&self) -> ProjectionMode {
+        self.projection
+    }
+
+    pub fn set_projection(&mut self, projection: ProjectionMode) {
+        self.projection = projection;
+    }
+
+    pub fn sky(&self) -> Sky {
+        self.sky
+    }
+
+    pub fn set_sky(&mut self, sky: Sky) {
+        self.sky = sky;
+    }
+
+    pub fn ambient_occlusion(&self) -> AmbientOcclusion {
+        self.ambient_occlusion
+    }
+
+    pub fn set_ambient_occlusion(&mut self, ambient_occlusion: AmbientOcclusion) {
+        self.ambient_occlusion = ambient_occlusion;
+    }
+
+    pub fn debug_normals(&self) -> bool {
+        self.debug_normals
+    }
+
+    /// shows the normal of each hit as a color instead of shading it
+    pub fn set_debug_normals(&mut self, debug_normals: bool) {
+        self.debug_normals = debug_normals;
+    }
+
+    pub fn light(&self) -> Option<Light> {
+        self.light
+    }
+
+    pub fn set_light(
+        &mut self,
+        position: cgmath::Vector4<f32>,
+        radius: f32,
+        color: cgmath::Vector3<f32>,
+    ) {
+        self.light = Some(Light {
+            position,
+            radius,
+            color,
+        });
+    }
+
+    pub fn remove_light(&mut self) {
+        self.light = None;
+    }
+
+    pub fn shadow_samples(&self) -> u32 {
+        self.shadow_samples
+    }
+
+    /// how many shadow rays are cast towards the light, soft shadows need at least 4 to look reasonable
+    pub fn set_shadow_samples(&mut self, shadow_samples: u32) {
+        self.shadow_samples = shadow_samples;
+    }
+
+    pub fn max_steps(&self) -> u32 {
+        self.max_steps
+    }
+
+    /// the most blocks a ray steps through, fewer is faster but can't see as far
+    pub fn set_max_steps(&mut self, max_steps: u32) {
+        self.max_steps = max_steps;
+    }
+
+    pub fn w_slice(&self) -> Option<f32> {
+        self.w_slice
+    }
+
+    /// only shows the blocks whose w coordinate is within half a block of the slice
+    pub fn set_w_slice(&mut self, w_slice: Option<f32>) {
+        self.w_slice = w_slice;
+    }
+
+    pub fn show_grid(&self) -> bool {
+        self.show_grid
+    }
+
+    /// draws one pixel wide lines on blocks at every integer coordinate
+    pub fn set_show_grid(&mut self, show_grid: bool) {
+        self.show_grid = show_grid;
+    }
+
+    pub fn grid_color(&self) -> cgmath::Vector4<f32> {
+        self.grid_color
+    }
+
+    /// the grid is blended over the blocks by the alpha of the color
+    pub fn set_grid_color(&mut self, grid_color: cgmath::Vector4<f32>) {
+        self.grid_color = grid_color;
+    }
+
+    pub fn show_chunk_bounds(&self) -> bool {
+        self.show_chunk_bounds
+    }
+
+    /// draws the edges of the chunk over the traced image, colored by the axis they run along
+    pub fn set_show_chunk_bounds(&mut self, show_chunk_bounds: bool) {
+        self.show_chunk_bounds = show_chunk_bounds;
+    }
+
+    pub fn block_colors(&self) -> &BlockColorTexture {
+        &self.block_colors
+    }
+
+    pub fn block_registry(&self) -> &BlockRegistry {
+        &self.block_registry
+    }
+
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
         queue: &wgpu::Queue,
         transform: Transform,
         basis: CameraBasis,
-        target: &RayTracingTarget,
+        target: &mut RayTracingTarget,
         encoder: &mut wgpu::CommandEncoder,
     ) {
-        let size = target.texture().texture_view().texture().size();
+        let size = target.render_texture().texture_view().texture().size();
+        let accumulation_size = self
+            .accumulation_target
+            .texture()
+            .texture_view()
+            .texture()
+            .size();
+        if self.accumulated && size != accumulation_size {
+            self.resize_accumulation_target(device, size.width, size.height);
+        }
+        target.accumulation_enabled = self.accumulated;
 
         {
             let x = transform.x().into();
@@ -110,14 +648,64 @@ impl RayTracing {
                 CameraBasis::XWZ => (x, w, z),
             };
 
-            let camera = GpuCamera {
+            let aspect = size.width as f32 / size.height as f32;
+            let (projection_scale, projection) = match self.projection {
+                ProjectionMode::Perspective { fov } => ((fov * 0.5).tan(), 0),
+                ProjectionMode::Orthographic { half_width } => (half_width / aspect, 1),
+            };
+
+            let (sky_top, sky_bottom) = match self.sky {
+                Sky::Color(color) => (color, color),
+                Sky::Gradient { top, bottom } => (top, bottom),
+            };
+
+            let light = self.light.unwrap_or(Light {
+                position: cgmath::vec4(0.0, 0.0, 0.0, 0.0),
+                radius: 0.0,
+                color: cgmath::vec3(0.0, 0.0, 0.0),
+            });
+
+            let chunk_bounds = self.chunk_bounds();
+            let mut camera = GpuCamera {
                 position: transform.position().into(),
                 forward,
                 up,
                 right,
-                aspect: size.width as f32 / size.height as f32,
+                sky_top: sky_top.into(),
+                sky_bottom: sky_bottom.into(),
+                light_position: light.position.into(),
+                light_color: light.color.extend(1.0).into(),
+                grid_color: self.grid_color.into(),
+                chunk_min: chunk_bounds.min.into(),
+                chunk_max: chunk_bounds.max.into(),
+                jitter: [0.0, 0.0],
+                aspect,
+                projection_scale,
+                projection,
+                accumulation_weight: 1.0,
+                ao_enabled: self.ambient_occlusion.enabled.into(),
+                ao_radius: self.ambient_occlusion.radius,
+                ao_strength: self.ambient_occlusion.strength,
+                debug_normals: self.debug_normals.into(),
+                light_radius: light.radius,
+                shadow_samples: self.shadow_samples,
+                max_steps: self.max_steps,
+                w_slice_enabled: self.w_slice.is_some().into(),
+                w_slice: self.w_slice.unwrap_or(0.0),
+                show_grid: self.show_grid.into(),
             };
-            queue.write_buffer(&target.camera_buffer, 0, bytemuck::bytes_of(&camera));
+            if self.last_camera.is_none_or(|last_camera| {
+                bytemuck::bytes_of(&last_camera) != bytemuck::bytes_of(&camera)
+            }) {
+                self.reset_accumulation();
+            }
+            self.last_camera = Some(camera);
+
+            camera.jitter = target.next_jitter();
+            if self.accumulated {
+                camera.accumulation_weight = self.accumulation_weight;
+            }
+            queue.write_buffer(target.camera_buffer(), 0, bytemuck::bytes_of(&camera));
         }
 
         let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
@@ -126,9 +714,130 @@ impl RayTracing {
         });
 
         compute_pass.set_pipeline(&self.ray_tracing_pipeline);
-        compute_pass.set_bind_group(0, &target.bind_group, &[]);
-        compute_pass.set_bind_group(1, &self.chunk_bind_group, &[]);
+        compute_pass.set_bind_group(0, target.bind_group(), &[]);
+        compute_pass.set_bind_group(1, self.chunk().bind_group(), &[]);
+        compute_pass.set_bind_group(2, self.block_colors.bind_group(), &[]);
+        compute_pass.set_bind_group(3, &self.accumulation_bind_group, &[]);
 
         compute_pass.dispatch_workgroups(size.width.div_ceil(16), size.height.div_ceil(16), 1);
+        drop(compute_pass);
+
+        if self.accumulated {
+            encoder.copy_texture_to_texture(
+                target
+                    .render_texture()
+                    .texture_view()
+                    .texture()
+                    .as_image_copy(),
+                self.accumulation_target
+                    .texture()
+                    .texture_view()
+                    .texture()
+                    .as_image_copy(),
+                size,
+            );
+            self.accumulation_weight = ACCUMULATION_WEIGHT;
+        }
+
+        // drawn after the copy so the lines don't end up in the accumulated history
+        if self.show_chunk_bounds {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Chunk Bounds Compute Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.chunk_bounds_pipeline);
+            compute_pass.set_bind_group(0, target.bind_group(), &[]);
+            compute_pass.dispatch_workgroups(size.width.div_ceil(16), size.height.div_ceil(16), 1);
+        }
+
+        if let Some(downsample_bind_group) = target.downsample_bind_group() {
+            let size = target.texture().texture_view().texture().size();
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Downsample Compute Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.downsample_pipeline);
+            compute_pass.set_bind_group(0, downsample_bind_group, &[]);
+            compute_pass.dispatch_workgroups(size.width.div_ceil(16), size.height.div_ceil(16), 1);
+        }
+
+        target.swap();
+    }
+}
+
+fn accumulation_bind_group(
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    accumulation_target: &RayTracingTarget,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Accumulation Bind Group"),
+        layout: bind_group_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(
+                accumulation_target.texture().texture_view(),
+            ),
+        }],
+    })
+}
+
+/// packs a block type id into the low 8 bits and an rgb tint into the rest,
+/// the tint is multiplied with the color of the block type, air should always be stored as 0
+pub fn pack_block(type_id: u8, r: u8, g: u8, b: u8) -> u32 {
+    u32::from_le_bytes([type_id, r, g, b])
+}
+
+/// returns (type id, r, g, b)
+pub fn unpack_block(block: u32) -> (u8, u8, u8, u8) {
+    let [type_id, r, g, b] = block.to_le_bytes();
+    (type_id, r, g, b)
+}
+
+/// returns (run length, value) pairs
+pub fn encode_rle(data: &[u32]) -> Vec<(u32, u32)> {
+    let mut encoded: Vec<(u32, u32)> = vec![];
+    for &value in data {
+        match encoded.last_mut() {
+            Some((length, last)) if *last == value && *length < u32::MAX => *length += 1,
+            _ => encoded.push((1, value)),
+        }
+    }
+    encoded
+}
+
+pub fn decode_rle(encoded: &[(u32, u32)], out: &mut [u32]) {
+    let mut offset = 0;
+    for &(length, value) in encoded {
+        out[offset..][..length as usize].fill(value);
+        offset += length as usize;
+    }
+    assert_eq!(offset, out.len(), "the runs should cover the whole output");
+}
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+struct GpuLighting {
+    sun_direction: [f32; 4],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_rle, encode_rle};
+
+    #[test]
+    fn rle_round_trip() {
+        let data = [&[0; 10][..], &[3], &[0; 5], &[9; 20]].concat();
+        let encoded = encode_rle(&data);
+        assert_eq!(encoded, [(10, 0), (1, 3), (5, 0), (20, 9)]);
+        let mut decoded = vec![u32::MAX; data.len()];
+        decode_rle(&encoded, &mut decoded);
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn rle_of_empty_data_is_empty() {
+        assert!(encode_rle(&[]).is_empty());
+        decode_rle(&[], &mut []);
     }
 }
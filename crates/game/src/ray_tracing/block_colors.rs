@@ -0,0 +1,136 @@
+use crate::{
+    bind_group_layout_cache::BindGroupLayoutCache,
+    ray_tracing::block_registry::{BlockRegistry, BlockType},
+};
+use std::sync::Arc;
+
+// row 0 holds the color of each block, row 1 holds its flags with emissive in the red channel
+
+pub struct BlockColorTexture {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+}
+
+impl BlockColorTexture {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bind_group_layout_cache: &BindGroupLayoutCache,
+        registry: &BlockRegistry,
+    ) -> Self {
+        let types = registry.types();
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Block Colors Texture"),
+            size: wgpu::Extent3d {
+                width: types
+                    .len()
+                    .max(1)
+                    .try_into()
+                    .expect("the number of block colors should fit in a u32"),
+                height: 2,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let texels = types
+            .iter()
+            .map(|block_type| block_type.color.into())
+            .chain(types.iter().map(flags))
+            .collect::<Vec<[f32; 4]>>();
+        queue.write_texture(
+            texture.as_image_copy(),
+            bytemuck::cast_slice(&texels),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some((types.len() * size_of::<[f32; 4]>()) as _),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: types.len() as _,
+                height: 2,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        let bind_group_layout = bind_group_layout(device, bind_group_layout_cache);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Block Colors Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(
+                    &texture.create_view(&Default::default()),
+                ),
+            }],
+        });
+
+        Self {
+            texture,
+            bind_group,
+        }
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue, block_type: &BlockType) {
+        let id = block_type.id;
+        assert!(
+            id < self.texture.width(),
+            "block id {id} should have a color in the block color texture"
+        );
+        let texels: [[f32; 4]; 2] = [block_type.color.into(), flags(block_type)];
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x: id, y: 0, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::cast_slice(&texels),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(size_of::<[f32; 4]>() as _),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 2,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    pub(super) fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+}
+
+fn flags(block_type: &BlockType) -> [f32; 4] {
+    [f32::from(u8::from(block_type.emissive)), 0.0, 0.0, 0.0]
+}
+
+pub(super) fn bind_group_layout(
+    device: &wgpu::Device,
+    bind_group_layout_cache: &BindGroupLayoutCache,
+) -> Arc<wgpu::BindGroupLayout> {
+    bind_group_layout_cache.get_or_create(
+        device,
+        &wgpu::BindGroupLayoutDescriptor {
+            label: Some("Block Colors Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                },
+                count: None,
+            }],
+        },
+    )
+}
@@ -0,0 +1,74 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockType {
+    pub id: u32,
+    pub name: String,
+    pub color: cgmath::Vector4<f32>,
+    /// emissive blocks ignore lighting and are drawn at full brightness
+    pub emissive: bool,
+}
+
+/// the block types, indexed by the ids stored in the chunk
+pub struct BlockRegistry {
+    types: Vec<BlockType>,
+}
+
+impl BlockRegistry {
+    pub const MAX_BLOCK_TYPES: usize = 256;
+
+    /// a registry containing only air, with id 0
+    pub fn new() -> Self {
+        Self {
+            types: vec![BlockType {
+                id: 0,
+                name: "air".into(),
+                color: cgmath::vec4(0.0, 0.0, 0.0, 0.0),
+                emissive: false,
+            }],
+        }
+    }
+
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        color: cgmath::Vector4<f32>,
+        emissive: bool,
+    ) -> u32 {
+        assert!(
+            self.types.len() < Self::MAX_BLOCK_TYPES,
+            "there should be at most {} block types",
+            Self::MAX_BLOCK_TYPES,
+        );
+        let id = self.types.len() as u32;
+        self.types.push(BlockType {
+            id,
+            name: name.into(),
+            color,
+            emissive,
+        });
+        id
+    }
+
+    pub fn get(&self, id: u32) -> Option<&BlockType> {
+        self.types.get(id as usize)
+    }
+
+    pub fn find(&self, name: &str) -> Option<&BlockType> {
+        self.types.iter().find(|block_type| block_type.name == name)
+    }
+
+    pub fn types(&self) -> &[BlockType] {
+        &self.types
+    }
+}
+
+impl Default for BlockRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register("stone", cgmath::vec4(0.6, 0.6, 0.6, 1.0), false);
+        registry.register("grass", cgmath::vec4(0.3, 0.7, 0.2, 1.0), false);
+        registry.register("dirt", cgmath::vec4(0.5, 0.35, 0.2, 1.0), false);
+        registry.register("sand", cgmath::vec4(0.9, 0.85, 0.6, 1.0), false);
+        registry.register("lamp", cgmath::vec4(1.0, 0.9, 0.6, 1.0), true);
+        registry
+    }
+}
@@ -0,0 +1,709 @@
+use crate::{
+    bind_group_layout_cache::BindGroupLayoutCache,
+    ray_tracing::{
+        chunk_file::{self, RunDecoder, RunEncoder},
+        encode_rle,
+        rle_decoder::RleDecoder,
+    },
+    validation::{ValidationFailure, ValidationScope},
+};
+use bytemuck::{Pod, Zeroable};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+    sync::Arc,
+};
+
+// chunks with more than this fraction of their blocks filled are stored densely
+const SPARSE_OCCUPANCY_LIMIT: f32 = 0.1;
+
+enum ChunkStorage {
+    /// the chunk buffer holds every block
+    Dense,
+    /// the chunk buffer holds (index, block) pairs of only the filled blocks, sorted by index,
+    /// they are mirrored here so reads and edits don't have to touch the gpu
+    Sparse(Vec<[u32; 2]>),
+}
+
+/// the resources every chunk needs to build its bind group and upload its blocks
+pub(super) struct ChunkShared {
+    pub bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    pub lighting_buffer: wgpu::Buffer,
+    pub rle_decoder: RleDecoder,
+}
+
+impl ChunkShared {
+    pub fn new(
+        device: &wgpu::Device,
+        bind_group_layout_cache: &BindGroupLayoutCache,
+        lighting_buffer: wgpu::Buffer,
+    ) -> Self {
+        Self {
+            bind_group_layout: bind_group_layout(device, bind_group_layout_cache),
+            lighting_buffer,
+            rle_decoder: RleDecoder::new(device, bind_group_layout_cache),
+        }
+    }
+}
+
+pub struct ChunkData {
+    size: usize,
+    buffer: wgpu::Buffer,
+    info_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    storage: ChunkStorage,
+    edits: BTreeMap<usize, u32>,
+}
+
+impl ChunkData {
+    /// an empty chunk
+    pub(super) fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shared: &ChunkShared,
+        size: usize,
+    ) -> Self {
+        let buffer = chunk_buffer(device, 2);
+        let info_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Chunk Info Uniform Buffer"),
+            size: size_of::<GpuChunkInfo>().next_multiple_of(16) as _,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = chunk_bind_group(
+            device,
+            &shared.bind_group_layout,
+            &buffer,
+            &shared.lighting_buffer,
+            &info_buffer,
+        );
+
+        let mut chunk = Self {
+            size,
+            buffer,
+            info_buffer,
+            bind_group,
+            storage: ChunkStorage::Sparse(vec![]),
+            edits: BTreeMap::new(),
+        };
+        chunk.upload_sparse(device, queue, shared, vec![]);
+        chunk
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub(super) fn has_edits(&self) -> bool {
+        !self.edits.is_empty()
+    }
+
+    pub(super) fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    pub(super) fn load(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shared: &mut ChunkShared,
+        path: &Path,
+    ) -> io::Result<()> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut decoder = RunDecoder::default();
+
+        #[cfg(feature = "chunk_json")]
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            let chunk_file::ChunkFile { chunk_size, runs } = serde_json::from_reader(reader)?;
+            self.check_size(chunk_size, self.size.pow(4) as _)?;
+
+            let mut runs = runs.into_iter();
+            return self.upload_slices(device, queue, shared, |blocks| {
+                decoder.fill(blocks, || {
+                    runs.next()
+                        .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))
+                })
+            });
+        }
+
+        let (chunk_size, block_count) = chunk_file::read_header(&mut reader)?;
+        self.check_size(chunk_size, block_count)?;
+        self.upload_slices(device, queue, shared, |blocks| {
+            decoder.fill(blocks, || chunk_file::read_run(&mut reader))
+        })
+    }
+
+    pub(super) fn save(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &Path,
+    ) -> io::Result<()> {
+        let mut encoder = RunEncoder::default();
+
+        #[cfg(feature = "chunk_json")]
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            let mut runs = vec![];
+            self.read_back_slices(device, queue, |blocks| {
+                encoder.push(blocks, |run| {
+                    runs.push(run);
+                    Ok(())
+                })
+            })?;
+            encoder.finish(|run| {
+                runs.push(run);
+                Ok(())
+            })?;
+
+            let chunk_file = chunk_file::ChunkFile {
+                chunk_size: self.size as _,
+                runs,
+            };
+            serde_json::to_writer(BufWriter::new(File::create(path)?), &chunk_file)?;
+            return Ok(());
+        }
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        chunk_file::write_header(&mut writer, self.size as _, self.size.pow(4) as _)?;
+        self.read_back_slices(device, queue, |blocks| {
+            encoder.push(blocks, |run| chunk_file::write_run(&mut writer, run))
+        })?;
+        encoder.finish(|run| chunk_file::write_run(&mut writer, run))?;
+        writer.flush()
+    }
+
+    fn check_size(&self, chunk_size: u32, block_count: u32) -> io::Result<()> {
+        if chunk_size as usize != self.size || block_count as usize != self.size.pow(4) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "expected a chunk of size {}, but the file has size {chunk_size} with {block_count} blocks",
+                    self.size
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    fn sparse_limit(&self) -> usize {
+        (self.size.pow(4) as f32 * SPARSE_OCCUPANCY_LIMIT) as usize
+    }
+
+    // only one w slice is ever in memory at a time, the chunk is stored sparsely
+    // unless it turns out to be too full
+    pub(super) fn upload_slices(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shared: &mut ChunkShared,
+        mut fill: impl FnMut(&mut [u32]) -> io::Result<()>,
+    ) -> io::Result<()> {
+        self.edits.clear();
+
+        let slice_length = self.size.pow(3);
+        let mut blocks = vec![0; slice_length];
+        let mut filled = Some(vec![]);
+        for w in 0..self.size {
+            fill(&mut blocks)?;
+            match &mut filled {
+                Some(filled_blocks) => {
+                    filled_blocks.extend(
+                        blocks
+                            .iter()
+                            .enumerate()
+                            .filter(|&(_, &block)| block != 0)
+                            .map(|(i, &block)| [(w * slice_length + i) as u32, block]),
+                    );
+                    if filled_blocks.len() > self.sparse_limit() {
+                        self.make_dense(device, queue, shared, filled_blocks);
+                        filled = None;
+                    }
+                }
+                None => self.write_dense_slice(device, queue, shared, w, &blocks),
+            }
+        }
+        if let Some(filled) = filled {
+            self.upload_sparse(device, queue, shared, filled);
+        }
+        Ok(())
+    }
+
+    /// replaces the whole chunk with the given (index, block) pairs, which should be sorted by index
+    pub(super) fn upload_filled(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shared: &mut ChunkShared,
+        filled: Vec<[u32; 2]>,
+    ) {
+        self.edits.clear();
+        if filled.len() > self.sparse_limit() {
+            self.make_dense(device, queue, shared, &filled);
+        } else {
+            self.upload_sparse(device, queue, shared, filled);
+        }
+    }
+
+    fn upload_sparse(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shared: &ChunkShared,
+        filled: Vec<[u32; 2]>,
+    ) {
+        let size = size_of_val::<[_]>(&filled) as wgpu::BufferAddress;
+        if matches!(self.storage, ChunkStorage::Dense) || size > self.buffer.size() {
+            // spare room is left so placing blocks doesn't need a new buffer every time
+            let capacity = (filled.len() * 2).clamp(1, self.sparse_limit().max(1));
+            self.set_buffer(
+                device,
+                shared,
+                chunk_buffer(device, capacity.max(filled.len()) * 2),
+            );
+        }
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&filled));
+
+        let chunk_info = GpuChunkInfo {
+            sparse: 1,
+            filled_count: filled.len() as _,
+        };
+        queue.write_buffer(&self.info_buffer, 0, bytemuck::bytes_of(&chunk_info));
+        self.storage = ChunkStorage::Sparse(filled);
+    }
+
+    fn make_dense(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shared: &mut ChunkShared,
+        filled: &[[u32; 2]],
+    ) {
+        self.set_buffer(device, shared, chunk_buffer(device, self.size.pow(4)));
+
+        // the new buffer starts out zeroed, so only slices with filled blocks need writing
+        let slice_length = self.size.pow(3);
+        let mut blocks = vec![0; slice_length];
+        for w in 0..self.size {
+            if sparse_slice(filled, w, &mut blocks) {
+                self.write_dense_slice(device, queue, shared, w, &blocks);
+            }
+        }
+
+        let chunk_info = GpuChunkInfo {
+            sparse: 0,
+            filled_count: 0,
+        };
+        queue.write_buffer(&self.info_buffer, 0, bytemuck::bytes_of(&chunk_info));
+        self.storage = ChunkStorage::Dense;
+    }
+
+    // slices that compress well are uploaded run-length encoded and expanded on the gpu
+    fn write_dense_slice(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shared: &mut ChunkShared,
+        w: usize,
+        blocks: &[u32],
+    ) {
+        let offset = w * blocks.len();
+        let runs = encode_rle(blocks);
+        if size_of_val::<[_]>(&runs) < size_of_val(blocks) {
+            shared
+                .rle_decoder
+                .decode(device, queue, &self.buffer, offset, &runs);
+        } else {
+            queue.write_buffer(
+                &self.buffer,
+                (offset * size_of::<u32>()) as _,
+                bytemuck::cast_slice(blocks),
+            );
+        }
+    }
+
+    fn set_buffer(&mut self, device: &wgpu::Device, shared: &ChunkShared, buffer: wgpu::Buffer) {
+        self.bind_group = chunk_bind_group(
+            device,
+            &shared.bind_group_layout,
+            &buffer,
+            &shared.lighting_buffer,
+            &self.info_buffer,
+        );
+        self.buffer = buffer;
+    }
+
+    fn read_back_slices(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mut read: impl FnMut(&[u32]) -> io::Result<()>,
+    ) -> io::Result<()> {
+        if let ChunkStorage::Sparse(filled) = &self.storage {
+            let mut blocks = vec![0; self.size.pow(3)];
+            for w in 0..self.size {
+                sparse_slice(filled, w, &mut blocks);
+                read(&blocks)?;
+            }
+            return Ok(());
+        }
+
+        let _validation_scope =
+            ValidationScope::new(device, "Chunk Read Back", ValidationFailure::Panic);
+
+        let slice_size = (self.size.pow(3) * size_of::<u32>()) as wgpu::BufferAddress;
+        let staging_buffer = read_back_buffer(device, slice_size);
+        for w in 0..self.size as wgpu::BufferAddress {
+            self.read_back(
+                device,
+                queue,
+                &staging_buffer,
+                &[(w * slice_size, slice_size)],
+                &mut read,
+            )??;
+        }
+        Ok(())
+    }
+
+    /// each `(offset, size)` range of the chunk buffer is copied after the previous one in `staging_buffer`
+    fn read_back<R>(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        staging_buffer: &wgpu::Buffer,
+        ranges: &[(wgpu::BufferAddress, wgpu::BufferAddress)],
+        read: impl FnOnce(&[u32]) -> R,
+    ) -> io::Result<R> {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Chunk Read Back Encoder"),
+        });
+        let mut staging_offset = 0;
+        for &(offset, size) in ranges {
+            encoder.copy_buffer_to_buffer(
+                &self.buffer,
+                offset,
+                staging_buffer,
+                staging_offset,
+                size,
+            );
+            staging_offset += size;
+        }
+        queue.submit([encoder.finish()]);
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        staging_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                _ = sender.send(result);
+            });
+        device
+            .poll(wgpu::PollType::Wait)
+            .map_err(io::Error::other)?;
+        receiver
+            .recv()
+            .expect("the map callback should have been called after waiting")
+            .map_err(io::Error::other)?;
+
+        let result = read(bytemuck::cast_slice(
+            &staging_buffer.slice(..).get_mapped_range(),
+        ));
+        staging_buffer.unmap();
+        Ok(result)
+    }
+
+    fn block_index(&self, position: cgmath::Vector4<u32>) -> usize {
+        let size = self.size;
+        let cgmath::Vector4 { x, y, z, w } = position.map(|c| c as usize);
+        assert!(
+            x < size && y < size && z < size && w < size,
+            "the block position should be inside the chunk",
+        );
+        x + size * (y + size * (z + size * w))
+    }
+
+    /// the edit is uploaded on the next `flush_blocks`
+    pub fn set_block(&mut self, position: cgmath::Vector4<u32>, block: u32) {
+        let index = self.block_index(position);
+        self.edits.insert(index, block);
+    }
+
+    /// looks up every position at once, dense chunks read all the blocks back from the gpu in a single copy
+    pub fn get_blocks(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        positions: &[cgmath::Vector4<u32>],
+    ) -> Vec<u32> {
+        let indices = positions
+            .iter()
+            .map(|&position| self.block_index(position))
+            .collect::<Vec<_>>();
+        let mut blocks = indices
+            .iter()
+            .map(|index| self.edits.get(index).copied())
+            .collect::<Vec<_>>();
+
+        match &self.storage {
+            ChunkStorage::Sparse(filled) => {
+                for (block, &index) in blocks.iter_mut().zip(&indices) {
+                    block.get_or_insert_with(|| {
+                        filled
+                            .binary_search_by_key(&(index as u32), |&[index, _]| index)
+                            .map_or(0, |i| filled[i][1])
+                    });
+                }
+            }
+            ChunkStorage::Dense => {
+                let ranges = blocks
+                    .iter()
+                    .zip(&indices)
+                    .filter(|(block, _)| block.is_none())
+                    .map(|(_, &index)| {
+                        let size = size_of::<u32>() as wgpu::BufferAddress;
+                        (index as wgpu::BufferAddress * size, size)
+                    })
+                    .collect::<Vec<_>>();
+                if !ranges.is_empty() {
+                    let _validation_scope =
+                        ValidationScope::new(device, "Block Read Back", ValidationFailure::Panic);
+                    let staging_buffer = read_back_buffer(
+                        device,
+                        (ranges.len() * size_of::<u32>()) as wgpu::BufferAddress,
+                    );
+                    let read = self
+                        .read_back(device, queue, &staging_buffer, &ranges, <[u32]>::to_vec)
+                        .expect("reading back blocks should not fail");
+                    let mut read = read.into_iter();
+                    for block in blocks.iter_mut().filter(|block| block.is_none()) {
+                        *block = read.next();
+                    }
+                }
+            }
+        }
+
+        blocks
+            .into_iter()
+            .map(|block| block.expect("every block should have been read"))
+            .collect()
+    }
+
+    pub(super) fn flush_blocks(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shared: &mut ChunkShared,
+    ) {
+        if self.edits.is_empty() {
+            return;
+        }
+
+        if let ChunkStorage::Sparse(filled) = &mut self.storage {
+            let mut filled = std::mem::take(filled);
+            let edits = std::mem::take(&mut self.edits);
+
+            // pairs before the first edit are untouched, the rest are merged with the edits in one pass
+            let (&first_edit, _) = edits
+                .first_key_value()
+                .expect("there should be an edit to flush");
+            let first_edit = first_edit as u32;
+            let start = filled.partition_point(|&[index, _]| index < first_edit);
+            let old_tail = filled.split_off(start);
+            let mut old_pairs = old_tail.iter().copied().peekable();
+            for (index, block) in edits {
+                let index = index as u32;
+                while let Some(pair) = old_pairs.next_if(|&[old_index, _]| old_index < index) {
+                    filled.push(pair);
+                }
+                old_pairs.next_if(|&[old_index, _]| old_index == index);
+                if block != 0 {
+                    filled.push([index, block]);
+                }
+            }
+            filled.extend(old_pairs);
+
+            let filled_size = size_of_val::<[_]>(&filled) as wgpu::BufferAddress;
+            if filled.len() > self.sparse_limit() || filled_size > self.buffer.size() {
+                self.upload_filled(device, queue, shared, filled);
+                return;
+            }
+
+            let new_tail = &filled[start..];
+            if new_tail.len() == old_tail.len() {
+                // nothing moved, so only the runs of pairs whose block changed are written
+                let mut i = 0;
+                while i < new_tail.len() {
+                    if new_tail[i] == old_tail[i] {
+                        i += 1;
+                        continue;
+                    }
+                    let run_start = i;
+                    while i < new_tail.len() && new_tail[i] != old_tail[i] {
+                        i += 1;
+                    }
+                    queue.write_buffer(
+                        &self.buffer,
+                        size_of_val::<[_]>(&filled[..start + run_start]) as _,
+                        bytemuck::cast_slice(&new_tail[run_start..i]),
+                    );
+                }
+            } else {
+                // a pair was added or removed, so everything after the first difference has shifted
+                let unchanged = new_tail
+                    .iter()
+                    .zip(&old_tail)
+                    .take_while(|(new, old)| new == old)
+                    .count();
+                if unchanged < new_tail.len() {
+                    queue.write_buffer(
+                        &self.buffer,
+                        size_of_val::<[_]>(&filled[..start + unchanged]) as _,
+                        bytemuck::cast_slice(&new_tail[unchanged..]),
+                    );
+                }
+
+                let chunk_info = GpuChunkInfo {
+                    sparse: 1,
+                    filled_count: filled.len() as _,
+                };
+                queue.write_buffer(&self.info_buffer, 0, bytemuck::bytes_of(&chunk_info));
+            }
+            self.storage = ChunkStorage::Sparse(filled);
+            return;
+        }
+
+        // edits to consecutive blocks are merged into a single write
+        let mut edits = std::mem::take(&mut self.edits).into_iter().peekable();
+        let mut blocks = vec![];
+        while let Some((start, block)) = edits.next() {
+            blocks.clear();
+            blocks.push(block);
+            while let Some((_, block)) = edits.next_if(|&(index, _)| index == start + blocks.len())
+            {
+                blocks.push(block);
+            }
+            queue.write_buffer(
+                &self.buffer,
+                (start * size_of::<u32>()) as _,
+                bytemuck::cast_slice(&blocks),
+            );
+        }
+    }
+}
+
+fn bind_group_layout(
+    device: &wgpu::Device,
+    bind_group_layout_cache: &BindGroupLayoutCache,
+) -> Arc<wgpu::BindGroupLayout> {
+    bind_group_layout_cache.get_or_create(
+        device,
+        &wgpu::BindGroupLayoutDescriptor {
+            label: Some("Chunk Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        },
+    )
+}
+
+fn chunk_buffer(device: &wgpu::Device, length: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Chunk Buffer"),
+        size: (length * size_of::<u32>()) as _,
+        usage: wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_DST
+            | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    })
+}
+
+fn chunk_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    chunk_buffer: &wgpu::Buffer,
+    lighting_buffer: &wgpu::Buffer,
+    chunk_info_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Chunk Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: chunk_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: lighting_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: chunk_info_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+/// fills `blocks` with the w slice of a sparse chunk, returns whether any of them are filled
+fn sparse_slice(filled: &[[u32; 2]], w: usize, blocks: &mut [u32]) -> bool {
+    let slice_length = blocks.len();
+    let start = filled.partition_point(|&[index, _]| (index as usize) < w * slice_length);
+    let end = filled.partition_point(|&[index, _]| (index as usize) < (w + 1) * slice_length);
+
+    blocks.fill(0);
+    for &[index, block] in &filled[start..end] {
+        blocks[index as usize - w * slice_length] = block;
+    }
+    start != end
+}
+
+fn read_back_buffer(device: &wgpu::Device, size: wgpu::BufferAddress) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Chunk Read Back Buffer"),
+        size,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+struct GpuChunkInfo {
+    sparse: u32,
+    filled_count: u32,
+}
@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+
+pub const MAGIC: [u8; 4] = *b"4DBC";
+
+/// `length` copies of `block` in a row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Run {
+    pub length: u32,
+    pub block: u32,
+}
+
+/// the json alternative to the binary format, it stores the same runs
+#[cfg(feature = "chunk_json")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkFile {
+    pub chunk_size: u32,
+    pub runs: Vec<Run>,
+}
+
+pub fn write_header(writer: &mut impl Write, chunk_size: u32, block_count: u32) -> io::Result<()> {
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&chunk_size.to_le_bytes())?;
+    writer.write_all(&block_count.to_le_bytes())?;
+    Ok(())
+}
+
+/// returns the chunk size and block count
+pub fn read_header(reader: &mut impl Read) -> io::Result<(u32, u32)> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a 4d blocks chunk file",
+        ));
+    }
+    let chunk_size = read_u32(reader)?;
+    let block_count = read_u32(reader)?;
+    Ok((chunk_size, block_count))
+}
+
+pub fn write_run(writer: &mut impl Write, run: Run) -> io::Result<()> {
+    writer.write_all(&run.length.to_le_bytes())?;
+    writer.write_all(&run.block.to_le_bytes())?;
+    Ok(())
+}
+
+pub fn read_run(reader: &mut impl Read) -> io::Result<Run> {
+    let length = read_u32(reader)?;
+    let block = read_u32(reader)?;
+    Ok(Run { length, block })
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// merges blocks into runs across multiple calls to `push`, so the chunk can be encoded a slice at a time
+#[derive(Default)]
+pub struct RunEncoder {
+    current: Option<Run>,
+}
+
+impl RunEncoder {
+    pub fn push(
+        &mut self,
+        blocks: &[u32],
+        mut emit: impl FnMut(Run) -> io::Result<()>,
+    ) -> io::Result<()> {
+        for &block in blocks {
+            match &mut self.current {
+                Some(run) if run.block == block && run.length < u32::MAX => run.length += 1,
+                current => {
+                    if let Some(run) = current.replace(Run { length: 1, block }) {
+                        emit(run)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(self, mut emit: impl FnMut(Run) -> io::Result<()>) -> io::Result<()> {
+        if let Some(run) = self.current {
+            emit(run)?;
+        }
+        Ok(())
+    }
+}
+
+/// expands runs back into blocks, a run may be split between calls to `fill`
+#[derive(Default)]
+pub struct RunDecoder {
+    current: Option<Run>,
+}
+
+impl RunDecoder {
+    pub fn fill(
+        &mut self,
+        blocks: &mut [u32],
+        mut next_run: impl FnMut() -> io::Result<Run>,
+    ) -> io::Result<()> {
+        let mut filled = 0;
+        while filled < blocks.len() {
+            let run = match &mut self.current {
+                Some(run) if run.length > 0 => run,
+                current => current.insert(next_run()?),
+            };
+            let length = (run.length as usize).min(blocks.len() - filled);
+            blocks[filled..][..length].fill(run.block);
+            run.length -= length as u32;
+            filled += length;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_round_trip() {
+        let blocks = [[0; 37], [5; 37], [0; 37], [7; 37]].concat();
+        let mut bytes = vec![];
+        write_header(&mut bytes, 4, blocks.len() as u32).unwrap();
+        let mut encoder = RunEncoder::default();
+        // push in uneven pieces so runs get merged across calls
+        for piece in blocks.chunks(10) {
+            encoder
+                .push(piece, |run| write_run(&mut bytes, run))
+                .unwrap();
+        }
+        encoder.finish(|run| write_run(&mut bytes, run)).unwrap();
+
+        let mut reader = bytes.as_slice();
+        assert_eq!(read_header(&mut reader).unwrap(), (4, blocks.len() as u32));
+        let mut decoded = vec![0; blocks.len()];
+        let mut decoder = RunDecoder::default();
+        // fill in different pieces so runs get split across calls
+        for piece in decoded.chunks_mut(16) {
+            decoder.fill(piece, || read_run(&mut reader)).unwrap();
+        }
+        assert_eq!(decoded, blocks);
+        assert!(reader.is_empty(), "every run should have been read");
+    }
+
+    #[test]
+    fn bad_magic_is_rejected() {
+        let mut bytes = vec![];
+        write_header(&mut bytes, 4, 0).unwrap();
+        bytes[0] = b'X';
+        let error = read_header(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::bind_group_layout_cache::BindGroupLayoutCache;
+use bytemuck::{Pod, Zeroable};
+use std::sync::Arc;
+
+/// expands run-length encoded blocks into the chunk buffer on the gpu
+pub struct RleDecoder {
+    runs_buffer: wgpu::Buffer,
+    info_buffer: wgpu::Buffer,
+    bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl RleDecoder {
+    pub fn new(device: &wgpu::Device, bind_group_layout_cache: &BindGroupLayoutCache) -> Self {
+        let runs_buffer = runs_buffer(device, 0);
+        let info_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("RLE Decode Info Uniform Buffer"),
+            size: size_of::<GpuDecodeInfo>().next_multiple_of(16) as _,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = bind_group_layout_cache.get_or_create(
+            device,
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("RLE Decode Bind Group Layout"),
+                entries: &[
+                    storage_entry(0, false),
+                    storage_entry(1, true),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            },
+        );
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!(concat!(
+            env!("OUT_DIR"),
+            "/shaders/decode_rle.wgsl"
+        )));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("RLE Decode Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("RLE Decode Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("decode_rle"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        Self {
+            runs_buffer,
+            info_buffer,
+            bind_group_layout,
+            pipeline,
+        }
+    }
+
+    /// writes `length` decoded blocks to `chunk_buffer` starting at block `offset`,
+    /// the work is submitted immediately so it runs before anything submitted afterwards
+    pub fn decode(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        chunk_buffer: &wgpu::Buffer,
+        offset: usize,
+        runs: &[(u32, u32)],
+    ) {
+        // each run is uploaded with the index it ends at, so every block can binary search for its run
+        let mut length = 0;
+        let gpu_runs = runs
+            .iter()
+            .map(|&(run_length, block)| {
+                length += run_length;
+                [length, block]
+            })
+            .collect::<Vec<_>>();
+
+        if size_of_val::<[_]>(&gpu_runs) as wgpu::BufferAddress > self.runs_buffer.size() {
+            self.runs_buffer = runs_buffer(device, gpu_runs.len());
+        }
+        queue.write_buffer(&self.runs_buffer, 0, bytemuck::cast_slice(&gpu_runs));
+
+        let info = GpuDecodeInfo {
+            offset: offset
+                .try_into()
+                .expect("the block offset should fit in a u32"),
+            length,
+            run_count: gpu_runs.len() as _,
+        };
+        queue.write_buffer(&self.info_buffer, 0, bytemuck::bytes_of(&info));
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("RLE Decode Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: chunk_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.runs_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.info_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("RLE Decode Encoder"),
+        });
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("RLE Decode Compute Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(length.div_ceil(256), 1, 1);
+        }
+        queue.submit([encoder.finish()]);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+struct GpuDecodeInfo {
+    offset: u32,
+    length: u32,
+    run_count: u32,
+}
+
+fn runs_buffer(device: &wgpu::Device, length: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("RLE Runs Buffer"),
+        size: (length.max(1) * size_of::<[u32; 2]>())
+            .try_into()
+            .expect("the size of the runs buffer should fit in a wgpu::BufferAddress"),
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
@@ -1,55 +1,297 @@
-use crate::ui::Texture;
+use crate::{
+    bind_group_layout_cache::BindGroupLayoutCache,
+    ui::{Texture, texture},
+};
 use bytemuck::{Pod, Zeroable};
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
 
 pub struct RayTracingTarget {
-    pub(super) texture: Texture,
-    pub(super) camera_buffer: wgpu::Buffer,
-    pub(super) bind_group: wgpu::BindGroup,
+    resources: Resources,
+    /// which of the two display textures is written to this frame, the other is shown
+    write_index: usize,
+    layouts: Layouts,
+    name: String,
+    filter: wgpu::FilterMode,
+    ssaa: u32,
+    jitter_sequence: [(f32, f32); 8],
+    jitter_index: usize,
+    pub accumulation_enabled: bool,
 }
 
 impl RayTracingTarget {
-    pub fn new(device: &wgpu::Device, name: &str, width: u32, height: u32) -> Self {
-        let texture = Texture::new(
+    /// `ssaa` is how many pixels along each axis are rendered and averaged for every displayed pixel
+    pub fn new(
+        device: &wgpu::Device,
+        bind_group_layout_cache: &BindGroupLayoutCache,
+        name: &str,
+        width: u32,
+        height: u32,
+        filter: wgpu::FilterMode,
+        ssaa: u32,
+    ) -> Self {
+        assert!(
+            matches!(ssaa, 1 | 2 | 4),
+            "the supersampling factor should be 1, 2, or 4"
+        );
+        let ssaa = clamp_ssaa(device, width, height, ssaa);
+
+        let layouts = Layouts {
+            texture: texture::bind_group_layout(
+                device,
+                bind_group_layout_cache,
+                wgpu::TextureFormat::Rgba32Float,
+            ),
+            write: bind_group_layout(device, bind_group_layout_cache),
+            downsample: downsample_bind_group_layout(device, bind_group_layout_cache),
+        };
+        let resources = layouts.create(device, name, width, height, filter, ssaa);
+
+        Self {
+            resources,
+            write_index: 0,
+            layouts,
+            name: name.to_owned(),
+            filter,
+            ssaa,
+            jitter_sequence: std::array::from_fn(|i| {
+                (halton(i as u32 + 1, 2) - 0.5, halton(i as u32 + 1, 3) - 0.5)
+            }),
+            jitter_index: 0,
+            accumulation_enabled: false,
+        }
+    }
+
+    /// recreates the textures at the new size, the bind group layouts stay the same
+    /// so the pipelines rendering to this target don't need to change
+    pub fn resize(&mut self, device: &wgpu::Device, new_width: u32, new_height: u32) -> &mut Self {
+        self.ssaa = clamp_ssaa(device, new_width, new_height, self.ssaa);
+        self.resources = self.layouts.create(
             device,
-            name,
-            width,
-            height,
-            wgpu::TextureUsages::STORAGE_BINDING,
-            wgpu::FilterMode::Nearest,
+            &self.name,
+            new_width,
+            new_height,
+            self.filter,
+            self.ssaa,
         );
+        self
+    }
 
-        let camera_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some(&format!("{name} Camera Uniform Buffer")),
-            size: size_of::<GpuCamera>().next_multiple_of(16) as _,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    pub fn ssaa(&self) -> u32 {
+        self.ssaa
+    }
+
+    /// the factor is lowered if the supersampled texture would be bigger than the device allows
+    pub fn set_ssaa(&mut self, device: &wgpu::Device, ssaa: u32) {
+        assert!(
+            matches!(ssaa, 1 | 2 | 4),
+            "the supersampling factor should be 1, 2, or 4"
+        );
+        self.ssaa = ssaa;
+
+        let size = self.texture().texture_view().texture().size();
+        self.resize(device, size.width, size.height);
+    }
+
+    /// whether `set_ssaa` would use `ssaa` as is at the current size
+    pub fn ssaa_fits(&self, device: &wgpu::Device, ssaa: u32) -> bool {
+        let size = self.texture().texture_view().texture().size();
+        clamp_ssaa(device, size.width, size.height, ssaa) == ssaa
+    }
+
+    /// copies the last frame rendered to this target back to the cpu, clamping it to 8 bit color
+    pub fn read_pixels(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> image::RgbaImage {
+        let texture = self.texture().texture_view().texture();
+        let size = texture.size();
+
+        // rows of a texture copy have to be padded to a multiple of 256 bytes
+        let unpadded_bytes_per_row = size.width * size_of::<[f32; 4]>() as u32;
+        let bytes_per_row =
+            unpadded_bytes_per_row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("{} Read Back Buffer", self.name)),
+            size: (bytes_per_row * size.height) as _,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
-        let bind_group_layout = bind_group_layout(device);
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some(&format!("{name} Write Bind Group")),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(texture.texture_view()),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: camera_buffer.as_entire_binding(),
-                },
-            ],
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some(&format!("{} Read Back Encoder", self.name)),
         });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &staging_buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+        queue.submit([encoder.finish()]);
 
-        Self {
-            texture,
-            camera_buffer,
-            bind_group,
+        let (sender, receiver) = std::sync::mpsc::channel();
+        staging_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                _ = sender.send(result);
+            });
+        device
+            .poll(wgpu::PollType::Wait)
+            .expect("waiting for the read back should not fail");
+        receiver
+            .recv()
+            .expect("the map callback should have been called after waiting")
+            .expect("mapping the read back buffer should not fail");
+
+        let mut image = image::RgbaImage::new(size.width, size.height);
+        {
+            let data = staging_buffer.slice(..).get_mapped_range();
+            for (y, row) in data.chunks_exact(bytes_per_row as usize).enumerate() {
+                let texels: &[[f32; 4]] =
+                    bytemuck::cast_slice(&row[..unpadded_bytes_per_row as usize]);
+                for (x, texel) in texels.iter().enumerate() {
+                    let pixel = texel.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+                    image.put_pixel(x as _, y as _, image::Rgba(pixel));
+                }
+            }
         }
+        staging_buffer.unmap();
+        image
     }
 
+    /// the display resolution output of the last finished frame
     pub fn texture(&self) -> &Texture {
-        &self.texture
+        &self.resources.textures[1 - self.write_index]
+    }
+
+    /// the display resolution texture the current frame ends up in
+    fn write_texture(&self) -> &Texture {
+        &self.resources.textures[self.write_index]
+    }
+
+    /// the texture the ray tracer writes to, which is larger than the display textures when supersampling
+    pub(super) fn render_texture(&self) -> &Texture {
+        self.resources
+            .render_texture
+            .as_ref()
+            .unwrap_or(self.write_texture())
+    }
+
+    pub(super) fn camera_buffer(&self) -> &wgpu::Buffer {
+        &self.resources.camera_buffer
+    }
+
+    pub(super) fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.resources.bind_groups[self.write_index]
+    }
+
+    /// the bind group for averaging `render_texture` into the write texture, if supersampling
+    pub(super) fn downsample_bind_group(&self) -> Option<&wgpu::BindGroup> {
+        self.resources
+            .downsample_bind_groups
+            .as_ref()
+            .map(|bind_groups| &bind_groups[self.write_index])
+    }
+
+    /// shows the frame that was just rendered, so the next one is written to the other texture
+    /// instead of the one the ui may still be sampling
+    pub(super) fn swap(&mut self) {
+        self.write_index = 1 - self.write_index;
+    }
+
+    // the sub-pixel offset to use for the next frame, in pixels
+    pub(super) fn next_jitter(&mut self) -> [f32; 2] {
+        if !self.accumulation_enabled {
+            return [0.0, 0.0];
+        }
+
+        let (x, y) = self.jitter_sequence[self.jitter_index];
+        self.jitter_index = (self.jitter_index + 1) % self.jitter_sequence.len();
+        [x, y]
+    }
+}
+
+/// the largest supported factor up to `ssaa` whose supersampled texture fits in `max_texture_dimension_2d`
+fn clamp_ssaa(device: &wgpu::Device, width: u32, height: u32, ssaa: u32) -> u32 {
+    let max_size = device.limits().max_texture_dimension_2d;
+    [4, 2, 1]
+        .into_iter()
+        .filter(|&factor| factor <= ssaa)
+        .find(|&factor| width.max(height).saturating_mul(factor) <= max_size)
+        .unwrap_or(1)
+}
+
+pub struct RayTracingTargetBuilder<'a> {
+    device: &'a wgpu::Device,
+    bind_group_layout_cache: &'a BindGroupLayoutCache,
+    name: Option<String>,
+    width: u32,
+    height: u32,
+    filter: wgpu::FilterMode,
+    ssaa: u32,
+}
+
+impl<'a> RayTracingTargetBuilder<'a> {
+    pub fn new(
+        device: &'a wgpu::Device,
+        bind_group_layout_cache: &'a BindGroupLayoutCache,
+    ) -> Self {
+        Self {
+            device,
+            bind_group_layout_cache,
+            name: None,
+            width: 1,
+            height: 1,
+            filter: wgpu::FilterMode::Linear,
+            ssaa: 1,
+        }
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn filter(mut self, filter: wgpu::FilterMode) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn ssaa(mut self, ssaa: u32) -> Self {
+        self.ssaa = ssaa;
+        self
+    }
+
+    pub fn default_name() -> String {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        format!(
+            "RayTracingTarget #{}",
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        )
+    }
+
+    pub fn build(self) -> RayTracingTarget {
+        let name = self.name.unwrap_or_else(Self::default_name);
+        RayTracingTarget::new(
+            self.device,
+            self.bind_group_layout_cache,
+            &name,
+            self.width,
+            self.height,
+            self.filter,
+            self.ssaa,
+        )
     }
 }
 
@@ -60,33 +302,212 @@ pub(crate) struct GpuCamera {
     pub(crate) forward: [f32; 4],
     pub(crate) up: [f32; 4],
     pub(crate) right: [f32; 4],
+    pub(crate) sky_top: [f32; 4],
+    pub(crate) sky_bottom: [f32; 4],
+    pub(crate) light_position: [f32; 4],
+    pub(crate) light_color: [f32; 4],
+    pub(crate) grid_color: [f32; 4],
+    pub(crate) chunk_min: [f32; 4],
+    pub(crate) chunk_max: [f32; 4],
+    pub(crate) jitter: [f32; 2],
     pub(crate) aspect: f32,
+    pub(crate) projection_scale: f32,
+    pub(crate) projection: u32,
+    pub(crate) accumulation_weight: f32,
+    pub(crate) ao_enabled: u32,
+    pub(crate) ao_radius: f32,
+    pub(crate) ao_strength: f32,
+    pub(crate) debug_normals: u32,
+    pub(crate) light_radius: f32,
+    pub(crate) shadow_samples: u32,
+    pub(crate) max_steps: u32,
+    pub(crate) w_slice_enabled: u32,
+    pub(crate) w_slice: f32,
+    pub(crate) show_grid: u32,
+}
+
+/// the gpu resources that depend on the size of the target,
+/// everything that touches the display textures comes in pairs so they can be swapped
+struct Resources {
+    textures: [Texture; 2],
+    render_texture: Option<Texture>,
+    camera_buffer: wgpu::Buffer,
+    bind_groups: [wgpu::BindGroup; 2],
+    downsample_bind_groups: Option<[wgpu::BindGroup; 2]>,
+}
+
+struct Layouts {
+    texture: Arc<wgpu::BindGroupLayout>,
+    write: Arc<wgpu::BindGroupLayout>,
+    downsample: Arc<wgpu::BindGroupLayout>,
+}
+
+impl Layouts {
+    fn create(
+        &self,
+        device: &wgpu::Device,
+        name: &str,
+        width: u32,
+        height: u32,
+        filter: wgpu::FilterMode,
+        ssaa: u32,
+    ) -> Resources {
+        let create_texture = |name: &str, width, height| {
+            Texture::with_layout(
+                device,
+                &self.texture,
+                name,
+                width,
+                height,
+                wgpu::TextureFormat::Rgba32Float,
+                wgpu::TextureUsages::STORAGE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC
+                    | wgpu::TextureUsages::COPY_DST,
+                filter,
+                wgpu::AddressMode::ClampToEdge,
+            )
+        };
+        let textures = [0, 1].map(|i| create_texture(&format!("{name} {i}"), width, height));
+        let render_texture = (ssaa > 1)
+            .then(|| create_texture(&format!("{name} Supersampled"), width * ssaa, height * ssaa));
+
+        let camera_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("{name} Camera Uniform Buffer")),
+            size: size_of::<GpuCamera>().next_multiple_of(16) as _,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_groups = [0, 1].map(|i| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(&format!("{name} Write Bind Group {i}")),
+                layout: &self.write,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(
+                            render_texture
+                                .as_ref()
+                                .unwrap_or(&textures[i])
+                                .texture_view(),
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: camera_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        });
+
+        let downsample_bind_groups = render_texture.as_ref().map(|render_texture| {
+            [0, 1].map(|i| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some(&format!("{name} Downsample Bind Group {i}")),
+                    layout: &self.downsample,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(
+                                render_texture.texture_view(),
+                            ),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::TextureView(
+                                textures[i].texture_view(),
+                            ),
+                        },
+                    ],
+                })
+            })
+        });
+
+        Resources {
+            textures,
+            render_texture,
+            camera_buffer,
+            bind_groups,
+            downsample_bind_groups,
+        }
+    }
 }
 
-pub(super) fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
-    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        label: Some("Texture Write Bind Group Layout"),
-        entries: &[
-            wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::COMPUTE,
-                ty: wgpu::BindingType::StorageTexture {
-                    access: wgpu::StorageTextureAccess::WriteOnly,
-                    format: wgpu::TextureFormat::Rgba32Float,
-                    view_dimension: wgpu::TextureViewDimension::D2,
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+pub(super) fn bind_group_layout(
+    device: &wgpu::Device,
+    bind_group_layout_cache: &BindGroupLayoutCache,
+) -> Arc<wgpu::BindGroupLayout> {
+    bind_group_layout_cache.get_or_create(
+        device,
+        &wgpu::BindGroupLayoutDescriptor {
+            label: Some("Texture Write Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::Rgba32Float,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
                 },
-                count: None,
-            },
-            wgpu::BindGroupLayoutEntry {
-                binding: 1,
-                visibility: wgpu::ShaderStages::COMPUTE,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
-                count: None,
-            },
-        ],
-    })
+            ],
+        },
+    )
+}
+
+pub(super) fn downsample_bind_group_layout(
+    device: &wgpu::Device,
+    bind_group_layout_cache: &BindGroupLayoutCache,
+) -> Arc<wgpu::BindGroupLayout> {
+    bind_group_layout_cache.get_or_create(
+        device,
+        &wgpu::BindGroupLayoutDescriptor {
+            label: Some("Downsample Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::Rgba32Float,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+        },
+    )
 }
@@ -1,34 +1,65 @@
 use crate::{
-    Input,
-    camera::Camera,
-    ray_tracing::{CameraBasis, RayTracing, RayTracingTarget},
-    ui::{Ellipse, Font, Line, Quad, TextureInfo, Ui},
+    AppConfig, Input,
+    bind_group_layout_cache::BindGroupLayoutCache,
+    bookmarks::{self, BOOKMARK_KEYS, Bookmarks},
+    camera::{Camera, PICK_DISTANCE},
+    debug_draw,
+    demo::HypercubeDemo,
+    ray_tracing::{CameraBasis, RayTracing, RayTracingTarget, RayTracingTargetBuilder, pack_block},
+    sun::SunAnimation,
+    ui::{
+        Ellipse, Font, Line, LineWidth, Quad, QuadBorder, Slider, TextAlign, TextSpan, TextureInfo,
+        TextureUploadQueue, Ui, font::format_number,
+    },
+    world_generator::{ProceduralSettings, WorldGenerator},
 };
-use cgmath::ElementWise;
+use cgmath::{ElementWise, InnerSpace};
+use math::{NoE2Rotor, dda::dda_4d};
+use std::path::Path;
+use winit::{event::MouseButton, keyboard::KeyCode};
 
 pub struct State {
     surface_width: u32,
     surface_height: u32,
+    bind_group_layout_cache: BindGroupLayoutCache,
 
     camera: Camera,
+    target_camera: Option<Camera>,
+    approach_speed: f32,
+    bookmarks: Bookmarks,
+    camera_basis: CameraBasis,
+    hypercube_demo: HypercubeDemo,
+    sun_animation: SunAnimation,
+    elapsed_time: f32,
+    show_frustum: bool,
+    show_help: bool,
 
     space_mono: Font,
     ui: Ui,
 
     frame_times: [f32; 128],
 
+    world_generator: WorldGenerator,
+    seed_text: String,
+    procedural_settings: ProceduralSettings,
+
     ray_tracing: RayTracing,
     main_view: RayTracingTarget,
+    w_slice_slider: Slider,
 }
 
 impl State {
-    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, config: &AppConfig) -> Self {
         let surface_width = 1;
         let surface_height = 1;
 
+        let bind_group_layout_cache = BindGroupLayoutCache::default();
+        let mut texture_uploads = TextureUploadQueue::default();
+
         let space_mono = Font::from_raw(
             device,
-            queue,
+            &bind_group_layout_cache,
+            &mut texture_uploads,
             include_str!("../fonts/space_mono.fnt"),
             &<_>::from([
                 (0, include_bytes!("../fonts/space_mono_0.png").as_slice()),
@@ -36,39 +67,385 @@ impl State {
             ]),
         );
 
-        let ray_tracing = RayTracing::new(device, queue);
-        let main_view =
-            RayTracingTarget::new(device, "Main View Texture", surface_width, surface_height);
+        let mut world_generator = WorldGenerator::new(config.seed);
+        let seed_text = format!("Seed: {}", world_generator.seed());
+        let ray_tracing = RayTracing::new(
+            device,
+            queue,
+            &bind_group_layout_cache,
+            &mut world_generator,
+        );
+        let main_view = RayTracingTargetBuilder::new(device, &bind_group_layout_cache)
+            .name("Main View Texture")
+            .size(surface_width, surface_height)
+            .build();
+
+        let ui = Ui::new(device, &bind_group_layout_cache, &mut texture_uploads);
 
-        Self {
+        let state = Self {
             surface_width,
             surface_height,
+            bind_group_layout_cache,
 
             camera: Camera::default(),
+            target_camera: None,
+            approach_speed: 5.0,
+            bookmarks: [None; _],
+            camera_basis: CameraBasis::XYZ,
+            hypercube_demo: HypercubeDemo::default(),
+            sun_animation: SunAnimation::default(),
+            elapsed_time: 0.0,
+            show_frustum: false,
+            show_help: false,
 
             space_mono,
-            ui: Ui::new(device, queue),
+            ui,
 
             frame_times: [0.0; _],
 
+            world_generator,
+            seed_text,
+            procedural_settings: ProceduralSettings::default(),
+
+            w_slice_slider: Slider::new(
+                cgmath::vec2(0.0, -0.9),
+                cgmath::vec2(1.0, 0.05),
+                0.0,
+                (ray_tracing.chunk_size() - 1) as f32,
+                0.0,
+            ),
             ray_tracing,
             main_view,
-        }
+        };
+        texture_uploads.flush(device, queue, &state.bind_group_layout_cache);
+        state
     }
 
-    pub fn update(&mut self, input: &Input, ts: f32) {
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, input: &Input, ts: f32) {
         self.frame_times.rotate_right(1);
         self.frame_times[0] = 1.0 / ts;
 
+        self.elapsed_time += ts;
+        self.ray_tracing
+            .set_lighting(queue, self.sun_animation.direction_at(self.elapsed_time));
+        self.ui.set_time(self.elapsed_time);
+
+        if input.key_just_pressed(KeyCode::F5) {
+            self.world_generator.reset(rand::random());
+            self.seed_text = format!("Seed: {}", self.world_generator.seed());
+            self.ray_tracing
+                .regenerate(device, queue, &mut self.world_generator);
+        }
+        if input.key_just_pressed(KeyCode::KeyP) && !self.ray_tracing.is_generating() {
+            self.procedural_settings.seed = rand::random();
+            self.ray_tracing.fill_procedural(self.procedural_settings);
+            self.seed_text = format!("Seed: {} (generating)", self.procedural_settings.seed);
+        }
+        if self.ray_tracing.finish_procedural(device, queue) {
+            self.seed_text = format!("Seed: {} (procedural)", self.procedural_settings.seed);
+        }
+
+        // the nether is created and generated the first time it is visited
+        if input.key_just_pressed(KeyCode::KeyN) {
+            let name = if self.ray_tracing.active_chunk() == "overworld" {
+                "nether"
+            } else {
+                "overworld"
+            };
+            let visited = self.ray_tracing.has_chunk(name);
+            if !visited {
+                self.ray_tracing.add_chunk(device, queue, name);
+            }
+            self.ray_tracing.set_active_chunk(name);
+            if !visited && !self.ray_tracing.is_generating() {
+                self.procedural_settings.seed = rand::random();
+                self.ray_tracing.fill_procedural(self.procedural_settings);
+            }
+        }
+
+        if input.key_just_pressed(KeyCode::F6)
+            && let Err(error) = self.camera.save_to_file(Path::new("camera.json"))
+        {
+            eprintln!("failed to save the camera: {error}");
+        }
+        if input.key_just_pressed(KeyCode::F6)
+            && let Err(error) =
+                bookmarks::save_to_file(&self.bookmarks, Path::new("bookmarks.json"))
+        {
+            eprintln!("failed to save the bookmarks: {error}");
+        }
+        if input.key_just_pressed(KeyCode::F7) {
+            match Camera::load_from_file(Path::new("camera.json")) {
+                Ok(camera) => self.target_camera = Some(camera),
+                Err(error) => eprintln!("failed to load the camera: {error}"),
+            }
+            match bookmarks::load_from_file(Path::new("bookmarks.json")) {
+                Ok(bookmarks) => self.bookmarks = bookmarks,
+                Err(error) => eprintln!("failed to load the bookmarks: {error}"),
+            }
+        }
+
+        if input.key_just_pressed(KeyCode::F12) {
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .expect("the system time should be after the unix epoch")
+                .as_secs();
+            let path = format!("screenshot_{timestamp}.png");
+            if let Err(error) = self.screenshot(device, queue).save(&path) {
+                eprintln!("failed to save the screenshot to {path}: {error}");
+            }
+        }
+
+        if input.key_just_pressed(KeyCode::F9)
+            && let Err(error) = self
+                .ray_tracing
+                .save_chunk(device, queue, Path::new("chunk.4dbc"))
+        {
+            eprintln!("failed to save the chunk: {error}");
+        }
+        if input.key_just_pressed(KeyCode::F10)
+            && let Err(error) = self
+                .ray_tracing
+                .load_chunk(device, queue, Path::new("chunk.4dbc"))
+        {
+            eprintln!("failed to load the chunk: {error}");
+        }
+
+        if input.mouse_button_just_pressed(MouseButton::Middle) {
+            // orbit the picked block, or the point it would be placed at if nothing is hit
+            self.camera.orbit_target = Some(match self.picked_block(device, queue) {
+                Some(position) => position.map(|c| c as f32 + 0.5),
+                None => self.camera.looked_at_block(),
+            });
+        }
+
+        if input.key_just_pressed(KeyCode::KeyB) {
+            let chunk_size = self.ray_tracing.chunk_size() as i32;
+            let target = self.camera.looked_at_block().map(|c| c.floor() as i32);
+
+            // the picked block is removed, otherwise stone is placed at the target
+            let in_chunk = [target.x, target.y, target.z, target.w]
+                .iter()
+                .all(|c| (0..chunk_size).contains(c));
+            if let Some(position) = self.picked_block(device, queue) {
+                self.ray_tracing.set_block(position, 0);
+            } else if in_chunk {
+                let stone = self
+                    .ray_tracing
+                    .block_registry()
+                    .find("stone")
+                    .expect("the default blocks should include stone")
+                    .id;
+                self.ray_tracing.set_block(
+                    target.map(|c| c as u32),
+                    pack_block(stone as u8, 255, 255, 255),
+                );
+            }
+        }
+        self.ray_tracing.flush_blocks(device, queue);
+
+        let control_pressed =
+            input.key_pressed(KeyCode::ControlLeft) || input.key_pressed(KeyCode::ControlRight);
+        for (bookmark, key) in self.bookmarks.iter_mut().zip(BOOKMARK_KEYS) {
+            if !input.key_just_pressed(key) {
+                continue;
+            }
+            if control_pressed {
+                *bookmark = Some(self.camera);
+            } else if let Some(camera) = *bookmark {
+                self.target_camera = Some(camera);
+            }
+        }
+
+        if input.key_just_pressed(KeyCode::F4) {
+            self.set_wireframe(!self.ui.wireframe());
+        }
+
+        if input.key_just_pressed(KeyCode::F2) {
+            self.ray_tracing
+                .set_debug_normals(!self.ray_tracing.debug_normals());
+        }
+
+        if input.key_just_pressed(KeyCode::F3) {
+            // factors too big for the device at this size are skipped
+            const SSAA_OPTIONS: [u32; 3] = [1, 2, 4];
+            let current = SSAA_OPTIONS
+                .iter()
+                .position(|&ssaa| ssaa == self.main_view.ssaa())
+                .unwrap_or(0);
+            let ssaa = (1..=SSAA_OPTIONS.len())
+                .map(|offset| SSAA_OPTIONS[(current + offset) % SSAA_OPTIONS.len()])
+                .find(|&ssaa| self.main_view.ssaa_fits(device, ssaa))
+                .unwrap_or(1);
+            self.main_view.set_ssaa(device, ssaa);
+        }
+
+        if input.key_just_pressed(KeyCode::F8) {
+            self.show_frustum = !self.show_frustum;
+        }
+
+        if input.key_just_pressed(KeyCode::F1) {
+            self.show_help = !self.show_help;
+        }
+
+        if input.key_just_pressed(KeyCode::KeyM) {
+            self.ray_tracing
+                .set_accumulated(!self.ray_tracing.accumulated());
+        }
+
+        if input.key_just_pressed(KeyCode::KeyG) {
+            let mut ambient_occlusion = self.ray_tracing.ambient_occlusion();
+            ambient_occlusion.enabled = !ambient_occlusion.enabled;
+            self.ray_tracing.set_ambient_occlusion(ambient_occlusion);
+        }
+        for (key, change) in [(KeyCode::Comma, -0.1), (KeyCode::Period, 0.1)] {
+            if input.key_just_pressed(key) {
+                let mut ambient_occlusion = self.ray_tracing.ambient_occlusion();
+                ambient_occlusion.strength = (ambient_occlusion.strength + change).clamp(0.0, 1.0);
+                self.ray_tracing.set_ambient_occlusion(ambient_occlusion);
+            }
+        }
+
+        if input.key_just_pressed(KeyCode::NumpadMultiply) {
+            let max_steps = self.ray_tracing.max_steps();
+            self.ray_tracing.set_max_steps((max_steps * 2).min(4096));
+        }
+        if input.key_just_pressed(KeyCode::NumpadDivide) {
+            let max_steps = self.ray_tracing.max_steps();
+            self.ray_tracing.set_max_steps((max_steps / 2).max(8));
+        }
+
+        if input.key_just_pressed(KeyCode::KeyV) {
+            let w_slice = match self.ray_tracing.w_slice() {
+                Some(_) => None,
+                None => Some(self.w_slice_slider.value),
+            };
+            self.ray_tracing.set_w_slice(w_slice);
+        }
+        if self.ray_tracing.w_slice().is_some() {
+            self.w_slice_slider.update(input);
+
+            let mut direction = 0.0;
+            if input.key_pressed(KeyCode::ArrowUp) {
+                direction += 1.0;
+            }
+            if input.key_pressed(KeyCode::ArrowDown) {
+                direction -= 1.0;
+            }
+            let slider = &mut self.w_slice_slider;
+            slider.value = (slider.value + direction * 4.0 * ts).clamp(slider.min, slider.max);
+
+            self.ray_tracing.set_w_slice(Some(slider.value));
+        }
+
+        if input.key_just_pressed(KeyCode::KeyH) {
+            self.ray_tracing
+                .set_show_grid(!self.ray_tracing.show_grid());
+        }
+
+        if input.key_just_pressed(KeyCode::KeyJ) {
+            self.ray_tracing
+                .set_show_chunk_bounds(!self.ray_tracing.show_chunk_bounds());
+        }
+
+        if input.key_just_pressed(KeyCode::KeyL) {
+            if self.ray_tracing.light().is_some() {
+                self.ray_tracing.remove_light();
+            } else {
+                self.ray_tracing
+                    .set_light(self.camera.position, 2.0, cgmath::vec3(1.0, 0.8, 0.6));
+            }
+        }
+
+        if input.key_just_pressed(KeyCode::KeyT) {
+            self.hypercube_demo.enabled = !self.hypercube_demo.enabled;
+        }
+        self.hypercube_demo.update(ts);
+
         self.camera.update(input, ts);
+        self.ray_tracing.set_projection(self.camera.projection());
+        // glides to a random orientation, to check the compass and camera interpolation
+        if input.key_just_pressed(KeyCode::KeyK) {
+            let mut target_camera = self.camera;
+            target_camera.rotation = NoE2Rotor::random(&mut rand::rng());
+            self.target_camera = Some(target_camera);
+        }
+
+        if let Some(target_camera) = self.target_camera {
+            // exponential approach, so the glide is independent of the frame rate
+            let t = 1.0 - (-self.approach_speed * ts).exp();
+            self.camera = Camera::lerp(&self.camera, &target_camera, t);
+            if self.camera.is_near(&target_camera, 0.001) {
+                self.camera = target_camera;
+                self.target_camera = None;
+            }
+        }
+    }
+
+    pub fn set_wireframe(&mut self, wireframe: bool) {
+        self.ui.set_wireframe(wireframe);
     }
 
-    pub fn surface_resized(&mut self, width: u32, height: u32) {
+    /// the first block along the view, up to the block `Camera::looked_at_block` would place
+    fn picked_block(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Option<cgmath::Vector4<u32>> {
+        let ray = self.camera.ray();
+        let chunk_size = self.ray_tracing.chunk_size() as i32;
+        let target = self.camera.looked_at_block().map(|c| c.floor() as i32);
+
+        let mut reached_target = false;
+        let candidates = dda_4d(ray.origin, ray.direction, (PICK_DISTANCE as u32 + 1) * 4)
+            .map(|(x, y, z, w)| cgmath::vec4(x, y, z, w))
+            .take_while(|&voxel| {
+                let before_target = !reached_target;
+                reached_target |= voxel == target;
+                before_target
+            })
+            .filter(|voxel| {
+                [voxel.x, voxel.y, voxel.z, voxel.w]
+                    .iter()
+                    .all(|c| (0..chunk_size).contains(c))
+            })
+            .map(|voxel| voxel.map(|c| c as u32))
+            .collect::<Vec<_>>();
+        // the candidates are read together so dense chunks only wait on the gpu once
+        let blocks = self.ray_tracing.get_blocks(device, queue, &candidates);
+        candidates
+            .into_iter()
+            .zip(blocks)
+            .find_map(|(position, block)| (block != 0).then_some(position))
+    }
+
+    pub fn surface_resized(&mut self, device: &wgpu::Device, width: u32, height: u32) {
         self.surface_width = width;
         self.surface_height = height;
+
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let main_view_size = self.main_view.texture().texture_view().texture().size();
+        if main_view_size.width != width || main_view_size.height != height {
+            self.main_view.resize(device, width, height);
+        }
+    }
+
+    /// the last rendered frame of the main view, without any ui
+    pub fn screenshot(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> image::RgbaImage {
+        self.main_view.read_pixels(device, queue)
     }
 
     pub fn mouse_moved(&mut self, input: &Input, old_position: cgmath::Vector2<f32>) {
+        // dragging the slider shouldn't also turn the camera
+        if self.ray_tracing.w_slice().is_some() && self.w_slice_slider.update(input) {
+            self.ray_tracing
+                .set_w_slice(Some(self.w_slice_slider.value));
+            return;
+        }
+
         let delta = input.mouse_position - old_position;
         self.camera.mouse_moved(input, delta);
     }
@@ -83,22 +460,12 @@ impl State {
 
         // render main view
         {
-            let main_view_size = self.main_view.texture().texture_view().texture().size();
-            if main_view_size.width != self.surface_width
-                || main_view_size.height != self.surface_height
-            {
-                self.main_view = RayTracingTarget::new(
-                    device,
-                    "Main View Texture",
-                    self.surface_width,
-                    self.surface_height,
-                );
-            }
             self.ray_tracing.render(
+                device,
                 queue,
                 self.camera.transform(),
-                CameraBasis::XYZ,
-                &self.main_view,
+                self.camera_basis,
+                &mut self.main_view,
                 encoder,
             );
         }
@@ -109,12 +476,10 @@ impl State {
                 position: cgmath::vec2(0.0, 0.0),
                 size: cgmath::vec2(2.0 * aspect, 2.0),
                 color: cgmath::vec4(1.0, 1.0, 1.0, 1.0),
+                gradient: None,
+                pulse_phase: None,
             },
-            Some(TextureInfo {
-                texture: self.main_view.texture().clone(),
-                uv_offset: cgmath::vec2(0.0, 0.0),
-                uv_size: cgmath::vec2(1.0, 1.0),
-            }),
+            Some(TextureInfo::whole(self.main_view.texture().clone())),
         );
 
         {
@@ -133,54 +498,345 @@ impl State {
 
             #[rustfmt::skip]
             let mut directions: [(cgmath::Vector4<f32>, cgmath::Vector3<f32>, &str); _] = [
-                (cgmath::vec4( 1.0, 0.0,  0.0,  0.0), cgmath::vec3(1.0, 0.0, 0.0), "+X"),
-                (cgmath::vec4(-1.0, 0.0,  0.0,  0.0), cgmath::vec3(1.0, 0.0, 0.0), "-X"),
-                (cgmath::vec4( 0.0, 0.0,  1.0,  0.0), cgmath::vec3(0.0, 0.0, 1.0), "+Z"),
-                (cgmath::vec4( 0.0, 0.0, -1.0,  0.0), cgmath::vec3(0.0, 0.0, 1.0), "-Z"),
-                (cgmath::vec4( 0.0, 0.0,  0.0,  1.0), cgmath::vec3(1.0, 0.0, 1.0), "+W"),
-                (cgmath::vec4( 0.0, 0.0,  0.0, -1.0), cgmath::vec3(1.0, 0.0, 1.0), "-W"),
+                (cgmath::vec4( 1.0,  0.0,  0.0,  0.0), cgmath::vec3(1.0, 0.0, 0.0), "+X"),
+                (cgmath::vec4(-1.0,  0.0,  0.0,  0.0), cgmath::vec3(1.0, 0.0, 0.0), "-X"),
+                (cgmath::vec4( 0.0,  1.0,  0.0,  0.0), cgmath::vec3(0.0, 0.8, 0.0), "+Y"),
+                (cgmath::vec4( 0.0, -1.0,  0.0,  0.0), cgmath::vec3(0.0, 0.8, 0.0), "-Y"),
+                (cgmath::vec4( 0.0,  0.0,  1.0,  0.0), cgmath::vec3(0.0, 0.0, 1.0), "+Z"),
+                (cgmath::vec4( 0.0,  0.0, -1.0,  0.0), cgmath::vec3(0.0, 0.0, 1.0), "-Z"),
+                (cgmath::vec4( 0.0,  0.0,  0.0,  1.0), cgmath::vec3(1.0, 0.0, 1.0), "+W"),
+                (cgmath::vec4( 0.0,  0.0,  0.0, -1.0), cgmath::vec3(1.0, 0.0, 1.0), "-W"),
             ];
             for (direction, _, _) in &mut directions {
-                *direction = self
-                    .camera
-                    .rotation
-                    .reverse()
-                    .transform_direction(*direction);
+                // the compass stays level, so only the y axis is tilted by the pitch of the camera
+                *direction = if direction.y != 0.0 {
+                    self.camera
+                        .rotor()
+                        .reverse()
+                        .transform_direction(*direction)
+                } else {
+                    self.camera
+                        .rotation
+                        .reverse()
+                        .transform_direction(*direction)
+                };
             }
-            directions.sort_by(|(a, _, _), (b, _, _)| a.w.total_cmp(&b.w));
+            let [forward_axis, _, right_axis] = self.camera_basis.axes();
+            let depth_axis = self.camera_basis.depth_axis();
+            directions.sort_by(|(a, _, _), (b, _, _)| a[depth_axis].total_cmp(&b[depth_axis]));
             for (direction, color, name) in directions {
+                let direction = cgmath::vec2(direction[right_axis], direction[forward_axis]);
                 self.ui.push_line(Line {
                     a: compass_position,
-                    b: compass_position
-                        + cgmath::vec2(direction.z, direction.x)
-                            .mul_element_wise(inner_compass_size * 0.5),
+                    b: compass_position + direction.mul_element_wise(inner_compass_size * 0.5),
                     color,
-                    width: 0.05,
+                    width: LineWidth::Ui(0.05),
                 });
 
-                self.space_mono.draw_str(
+                self.space_mono.draw_str_aligned(
                     &mut self.ui,
                     name,
-                    compass_position
-                        + cgmath::vec2(direction.z, direction.x)
-                            .mul_element_wise(inner_compass_size * 0.45),
+                    compass_position + direction.mul_element_wise(inner_compass_size * 0.45),
                     0.1,
                     cgmath::vec4(0.0, 0.0, 0.0, 1.0),
+                    TextAlign::Center,
+                );
+            }
+
+            // bookmarks are shown in the direction they are in from the camera
+            for (i, bookmark) in self.bookmarks.iter().enumerate() {
+                let Some(bookmark) = bookmark else {
+                    continue;
+                };
+                let offset = self
+                    .camera
+                    .rotation
+                    .reverse()
+                    .transform_direction(bookmark.position - self.camera.position);
+                let mut direction = cgmath::vec2(offset[right_axis], offset[forward_axis]);
+                if direction.magnitude2() > 1.0 {
+                    direction = direction.normalize();
+                }
+                let position =
+                    compass_position + direction.mul_element_wise(inner_compass_size * 0.5);
+
+                self.ui.push_ellipse(
+                    Ellipse {
+                        position,
+                        size: cgmath::vec2(0.04, 0.04),
+                        color: cgmath::vec4(0.9, 0.5, 0.1, 1.0),
+                    },
+                    None,
+                );
+                self.space_mono.draw_int(
+                    &mut self.ui,
+                    i as _,
+                    position + cgmath::vec2(0.02, 0.02),
+                    0.06,
+                    cgmath::vec4(0.0, 0.0, 0.0, 1.0),
                 );
             }
         }
 
         {
             let fps = self.frame_times.iter().sum::<f32>() / self.frame_times.len() as f32;
+            // left aligned, so the label doesn't move as the number of digits changes
+            let mut buffer = [0; 32];
+            let fps_rect = self.space_mono.draw_spans(
+                &mut self.ui,
+                &[
+                    TextSpan {
+                        text: "FPS: ",
+                        scale: 0.1,
+                        color: cgmath::vec4(1.0, 1.0, 1.0, 1.0),
+                    },
+                    TextSpan {
+                        text: format_number(&mut buffer, fps.into(), 2),
+                        scale: 0.1,
+                        color: cgmath::vec4(0.4, 1.0, 0.4, 1.0),
+                    },
+                ],
+                cgmath::vec2(-0.24, 0.95),
+                TextAlign::Left,
+            );
             self.space_mono.draw_str(
                 &mut self.ui,
-                &format!("FPS: {fps:.2}"),
-                cgmath::vec2(0.0, 0.95),
-                0.1,
+                "Speed:",
+                cgmath::vec2(0.7, 0.95),
+                0.06,
+                cgmath::vec4(1.0, 1.0, 1.0, 1.0),
+            );
+            self.space_mono.draw_number(
+                &mut self.ui,
+                self.camera.speed().into(),
+                2,
+                cgmath::vec2(0.9, 0.95),
+                0.06,
                 cgmath::vec4(1.0, 1.0, 1.0, 1.0),
             );
+            self.space_mono.draw_str(
+                &mut self.ui,
+                "FOV:",
+                cgmath::vec2(0.7, 0.89),
+                0.06,
+                cgmath::vec4(1.0, 1.0, 1.0, 1.0),
+            );
+            self.space_mono.draw_number(
+                &mut self.ui,
+                self.camera.fov_radians.to_degrees().into(),
+                0,
+                cgmath::vec2(0.9, 0.89),
+                0.06,
+                cgmath::vec4(1.0, 1.0, 1.0, 1.0),
+            );
+            self.space_mono.draw_str(
+                &mut self.ui,
+                &self.seed_text,
+                cgmath::vec2(0.0, fps_rect.y - 0.05),
+                0.06,
+                cgmath::vec4(1.0, 1.0, 1.0, 1.0),
+            );
+            self.space_mono.draw_str(
+                &mut self.ui,
+                if self.camera.ground_movement {
+                    "Movement: Ground"
+                } else {
+                    "Movement: Flying"
+                },
+                cgmath::vec2(0.0, fps_rect.y - 0.11),
+                0.06,
+                cgmath::vec4(1.0, 1.0, 1.0, 1.0),
+            );
+            let transform = self.camera.transform();
+            let axes = [transform.x(), transform.y(), transform.z(), transform.w()];
+            let [forward_axis, up_axis, right_axis] = self.camera_basis.axes();
+            let depth_axis = self.camera_basis.depth_axis();
+            let orientation = format!(
+                "Fwd {} Up {} Right {} Ana {}",
+                closest_axis_name(axes[forward_axis]),
+                closest_axis_name(axes[up_axis]),
+                closest_axis_name(axes[right_axis]),
+                closest_axis_name(axes[depth_axis]),
+            );
+            self.space_mono.draw_str(
+                &mut self.ui,
+                &orientation,
+                cgmath::vec2(0.0, fps_rect.y - 0.17),
+                0.06,
+                cgmath::vec4(1.0, 1.0, 1.0, 1.0),
+            );
+            self.space_mono.draw_str(
+                &mut self.ui,
+                &self.camera.position_string(),
+                cgmath::vec2(0.0, fps_rect.y - 0.23),
+                0.06,
+                cgmath::vec4(1.0, 1.0, 1.0, 1.0),
+            );
+            // padded to the same width as the position above, so the text doesn't move
+            let distance = format_number(&mut buffer, self.camera.position.magnitude().into(), 2);
+            let padding = &"        "[distance.len().min(8)..];
+            self.space_mono.draw_spans(
+                &mut self.ui,
+                &["Distance: ", padding, distance].map(|text| TextSpan {
+                    text,
+                    scale: 0.06,
+                    color: cgmath::vec4(1.0, 1.0, 1.0, 1.0),
+                }),
+                cgmath::vec2(0.0, fps_rect.y - 0.29),
+                TextAlign::Center,
+            );
+            if self.ui.wireframe() {
+                let text = if self.ui.supports_wireframe() {
+                    "WIREFRAME"
+                } else {
+                    "WIREFRAME (unsupported)"
+                };
+                self.space_mono.draw_str(
+                    &mut self.ui,
+                    text,
+                    cgmath::vec2(0.0, fps_rect.y - 0.35),
+                    0.06,
+                    cgmath::vec4(1.0, 0.3, 0.3, 1.0),
+                );
+            }
+        }
+
+        // ambient occlusion strength slider
+        {
+            let ambient_occlusion = self.ray_tracing.ambient_occlusion();
+            let slider_size = cgmath::vec2(0.5, 0.06);
+            let slider_position = cgmath::vec2(-aspect + 0.2 + slider_size.x * 0.5, -0.9);
+            let color = if ambient_occlusion.enabled {
+                cgmath::vec4(1.0, 1.0, 1.0, 1.0)
+            } else {
+                cgmath::vec4(0.5, 0.5, 0.5, 1.0)
+            };
+
+            self.space_mono.draw_str_aligned(
+                &mut self.ui,
+                "AO",
+                cgmath::vec2(-aspect + 0.05, -0.9),
+                0.06,
+                color,
+                TextAlign::Left,
+            );
+            let filled_width = slider_size.x * ambient_occlusion.strength;
+            self.ui.push_quad(
+                Quad {
+                    position: cgmath::vec2(
+                        slider_position.x - (slider_size.x - filled_width) * 0.5,
+                        slider_position.y,
+                    ),
+                    size: cgmath::vec2(filled_width, slider_size.y),
+                    color: color.mul_element_wise(cgmath::vec4(1.0, 1.0, 1.0, 0.6)),
+                    gradient: None,
+                    pulse_phase: None,
+                },
+                None,
+            );
+            self.ui.push_quad_border(QuadBorder {
+                position: slider_position,
+                size: slider_size,
+                color,
+                thickness: 0.01,
+            });
         }
 
+        if let Some(w_slice) = self.ray_tracing.w_slice() {
+            self.w_slice_slider.draw(&mut self.ui);
+            let mut buffer = [0; 32];
+            self.space_mono.draw_spans(
+                &mut self.ui,
+                &["W: ", format_number(&mut buffer, w_slice.into(), 1)].map(|text| TextSpan {
+                    text,
+                    scale: 0.06,
+                    color: cgmath::vec4(1.0, 1.0, 1.0, 1.0),
+                }),
+                self.w_slice_slider.position
+                    - cgmath::vec2(self.w_slice_slider.size.x * 0.5 + 0.05, 0.0),
+                TextAlign::Right,
+            );
+        }
+
+        if self.show_frustum {
+            debug_draw::draw_frustum(
+                &mut self.ui,
+                self.camera_basis,
+                self.camera.fov_radians,
+                aspect,
+                0.1,
+                64.0,
+                cgmath::vec3(1.0, 1.0, 0.3),
+            );
+        }
+
+        if self.show_help {
+            self.ui.push_quad(
+                Quad {
+                    position: cgmath::vec2(0.0, 0.0),
+                    size: cgmath::vec2(1.4, 1.7),
+                    color: cgmath::vec4(0.0, 0.0, 0.0, 0.8),
+                    gradient: None,
+                    pulse_phase: None,
+                },
+                None,
+            );
+
+            let mut text_y = 0.75;
+            for line in [
+                "WASD: move",
+                "Q/E: down/up",
+                "R/F: ana/kata",
+                "Left drag: XZ and XY",
+                "Right drag: ZW and XW",
+                "[ and ]: rotate YW",
+                "; and ': rotate YZ",
+                "Middle click: orbit the block ahead",
+                "Escape: stop orbiting",
+                "= and -: change speed",
+                "Numpad + and -: change FOV",
+                "Numpad * and /: change max ray steps",
+                "O: perspective/orthographic",
+                "C: ground/flying movement",
+                "Home: reset the camera",
+                "0-9: go to bookmark",
+                "Ctrl+0-9: save bookmark",
+                "F1: toggle help",
+                "F2: toggle normals view",
+                "F3: cycle supersampling 1x/2x/4x",
+                "F4: toggle wireframe",
+                "F5: regenerate the world",
+                "F6/F7: save/load camera",
+                "F8: toggle frustum",
+                "F9/F10: save/load chunk",
+                "F12: save screenshot",
+                "P: generate procedural world",
+                "B: place/remove block",
+                "N: switch between overworld/nether",
+                "M: toggle frame accumulation",
+                "G: toggle ambient occlusion",
+                ", and .: change AO strength",
+                "L: place/remove light",
+                "H: toggle grid",
+                "J: toggle chunk bounds",
+                "K: glide to a random orientation",
+                "V: toggle W slice, drag or Up/Down to move it",
+                "T: rotation tutorial",
+            ] {
+                self.space_mono.draw_str(
+                    &mut self.ui,
+                    line,
+                    cgmath::vec2(0.0, text_y),
+                    0.06,
+                    cgmath::vec4(1.0, 1.0, 1.0, 1.0),
+                );
+                text_y -= 0.075;
+            }
+        }
+
+        self.hypercube_demo
+            .draw(&mut self.ui, &self.space_mono, &self.camera, aspect);
+
         move |render_pass: &mut wgpu::RenderPass<'_>| {
             self.ui.render(
                 device,
@@ -199,6 +855,7 @@ pub(crate) fn render_pipeline(
     layout: &wgpu::PipelineLayout,
     shader: &wgpu::ShaderModule,
     topology: wgpu::PrimitiveTopology,
+    polygon_mode: wgpu::PolygonMode,
 ) -> wgpu::RenderPipeline {
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: Some(name),
@@ -215,7 +872,7 @@ pub(crate) fn render_pipeline(
             front_face: wgpu::FrontFace::Cw,
             cull_mode: None,
             unclipped_depth: false,
-            polygon_mode: wgpu::PolygonMode::Fill,
+            polygon_mode,
             conservative: false,
         },
         depth_stencil: None,
@@ -238,3 +895,12 @@ pub(crate) fn render_pipeline(
         cache: None,
     })
 }
+
+/// the name of the axis direction closest to the direction, like "+X" or "-W"
+fn closest_axis_name(direction: cgmath::Vector4<f32>) -> &'static str {
+    const NAMES: [[&str; 2]; 4] = [["+X", "-X"], ["+Y", "-Y"], ["+Z", "-Z"], ["+W", "-W"]];
+    let axis = (0..4)
+        .max_by(|&a, &b| direction[a].abs().total_cmp(&direction[b].abs()))
+        .expect("there should be 4 axes");
+    NAMES[axis][usize::from(direction[axis] < 0.0)]
+}
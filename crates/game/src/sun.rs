@@ -0,0 +1,29 @@
+use std::f32::consts::TAU;
+
+pub struct SunAnimation {
+    pub period_seconds: f32,
+    // the two axes the sun orbits through, starting along the first
+    pub orbit_plane: (usize, usize),
+}
+
+impl Default for SunAnimation {
+    fn default() -> Self {
+        Self {
+            period_seconds: 120.0,
+            orbit_plane: (1, 3),
+        }
+    }
+}
+
+impl SunAnimation {
+    pub fn direction_at(&self, t: f32) -> cgmath::Vector4<f32> {
+        let angle = TAU * t / self.period_seconds;
+        let (a, b) = self.orbit_plane;
+        assert_ne!(a, b, "the orbit plane should be made of two different axes");
+
+        let mut direction = cgmath::vec4(0.0, 0.0, 0.0, 0.0);
+        direction[a] = angle.cos();
+        direction[b] = angle.sin();
+        direction
+    }
+}
@@ -1,29 +1,114 @@
 pub mod font;
+pub mod slider;
 pub mod texture;
 
-pub use {font::Font, texture::Texture};
+pub use {
+    font::{Font, TextAlign, TextOutline, TextSpan, TextStyle},
+    slider::Slider,
+    texture::{Texture, TextureAtlas, TextureUploadQueue},
+};
 
-use crate::state::render_pipeline;
+use crate::{bind_group_layout_cache::BindGroupLayoutCache, state::render_pipeline};
 use bytemuck::{Pod, Zeroable};
-use std::num::NonZeroU64;
+use std::{num::NonZeroU64, ops::Range, sync::Arc};
 
+#[derive(Clone)]
 pub struct TextureInfo {
     pub texture: Texture,
     pub uv_offset: cgmath::Vector2<f32>,
     pub uv_size: cgmath::Vector2<f32>,
+    /// in radians, counterclockwise around the center of the uv region, applied after flipping
+    pub uv_rotation: f32,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    /// how many times the texture repeats across a quad, this is ignored unless the region
+    /// is the whole texture, since repeating part of an atlas would show its neighbours
+    pub uv_tile_scale: cgmath::Vector2<f32>,
+}
+
+impl TextureInfo {
+    /// the whole texture, the right way up
+    pub fn whole(texture: Texture) -> Self {
+        Self {
+            texture,
+            uv_offset: cgmath::vec2(0.0, 0.0),
+            uv_size: cgmath::vec2(1.0, 1.0),
+            uv_rotation: 0.0,
+            flip_x: false,
+            flip_y: false,
+            uv_tile_scale: cgmath::vec2(1.0, 1.0),
+        }
+    }
+}
+
+pub enum LineWidth {
+    Ui(f32),
+    ScreenPixels(f32),
 }
 
 pub struct Line {
     pub a: cgmath::Vector2<f32>,
     pub b: cgmath::Vector2<f32>,
     pub color: cgmath::Vector3<f32>,
-    pub width: f32,
+    pub width: LineWidth,
 }
 
 pub struct Quad {
     pub position: cgmath::Vector2<f32>,
     pub size: cgmath::Vector2<f32>,
     pub color: cgmath::Vector4<f32>,
+    pub gradient: Option<QuadGradient>,
+    /// when set, the alpha pulses with the ui time, offset by this phase in radians
+    pub pulse_phase: Option<f32>,
+}
+
+impl Quad {
+    pub fn with_gradient(
+        self,
+        color_a: cgmath::Vector4<f32>,
+        color_b: cgmath::Vector4<f32>,
+        angle: f32,
+    ) -> Self {
+        Self {
+            color: color_a,
+            gradient: Some(QuadGradient {
+                color: color_b,
+                angle,
+            }),
+            ..self
+        }
+    }
+
+    pub fn pulsing(self, phase: f32) -> Self {
+        Self {
+            pulse_phase: Some(phase),
+            ..self
+        }
+    }
+}
+
+/// fades from the quad's color to `color` along `angle`, in radians from the +x axis
+pub struct QuadGradient {
+    pub color: cgmath::Vector4<f32>,
+    pub angle: f32,
+}
+
+pub struct QuadBorder {
+    pub position: cgmath::Vector2<f32>,
+    pub size: cgmath::Vector2<f32>,
+    pub color: cgmath::Vector4<f32>,
+    pub thickness: f32,
+}
+
+/// a textured panel whose corners keep their size while the edges and center stretch,
+/// the texture region is split into a 3x3 grid with `texture_border` texel wide corners,
+/// which are drawn `border` ui units wide
+pub struct NineSlice {
+    pub position: cgmath::Vector2<f32>,
+    pub size: cgmath::Vector2<f32>,
+    pub color: cgmath::Vector4<f32>,
+    pub border: f32,
+    pub texture_border: f32,
 }
 
 pub struct Ellipse {
@@ -32,6 +117,62 @@ pub struct Ellipse {
     pub color: cgmath::Vector4<f32>,
 }
 
+pub struct RoundedRect {
+    pub position: cgmath::Vector2<f32>,
+    pub size: cgmath::Vector2<f32>,
+    pub corner_radius: f32,
+    pub color: cgmath::Vector4<f32>,
+}
+
+pub struct EllipseArc {
+    pub position: cgmath::Vector2<f32>,
+    pub radius: cgmath::Vector2<f32>,
+    /// in radians, counterclockwise from the +x axis
+    pub start_angle: f32,
+    pub end_angle: f32,
+    pub color: cgmath::Vector4<f32>,
+    pub width: f32,
+}
+
+pub struct Triangle {
+    pub a: cgmath::Vector2<f32>,
+    pub b: cgmath::Vector2<f32>,
+    pub c: cgmath::Vector2<f32>,
+    pub color: cgmath::Vector4<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub position: cgmath::Vector2<f32>,
+    pub size: cgmath::Vector2<f32>,
+}
+
+impl Rect {
+    fn min(&self) -> cgmath::Vector2<f32> {
+        self.position - self.size * 0.5
+    }
+
+    fn max(&self) -> cgmath::Vector2<f32> {
+        self.position + self.size * 0.5
+    }
+
+    fn intersection(&self, other: &Rect) -> Rect {
+        let min = cgmath::vec2(
+            self.min().x.max(other.min().x),
+            self.min().y.max(other.min().y),
+        );
+        let max = cgmath::vec2(
+            self.max().x.min(other.max().x),
+            self.max().y.min(other.max().y),
+        );
+        let size = cgmath::vec2((max.x - min.x).max(0.0), (max.y - min.y).max(0.0));
+        Rect {
+            position: min + size * 0.5,
+            size,
+        }
+    }
+}
+
 pub struct Ui {
     white_pixel_texture: Texture,
 
@@ -39,55 +180,84 @@ pub struct Ui {
     camera_bind_group: wgpu::BindGroup,
 
     lines_buffer: wgpu::Buffer,
-    lines_bind_group_layout: wgpu::BindGroupLayout,
+    lines_bind_group_layout: Arc<wgpu::BindGroupLayout>,
     lines_bind_group: wgpu::BindGroup,
     lines_pipeline: wgpu::RenderPipeline,
+    lines_wireframe_pipeline: Option<wgpu::RenderPipeline>,
 
     quads_buffer: wgpu::Buffer,
-    quads_bind_group_layout: wgpu::BindGroupLayout,
+    quads_bind_group_layout: Arc<wgpu::BindGroupLayout>,
     quads_bind_group: wgpu::BindGroup,
     quads_pipeline: wgpu::RenderPipeline,
+    quads_wireframe_pipeline: Option<wgpu::RenderPipeline>,
 
     ellipses_buffer: wgpu::Buffer,
-    ellipses_bind_group_layout: wgpu::BindGroupLayout,
+    ellipses_bind_group_layout: Arc<wgpu::BindGroupLayout>,
     ellipses_bind_group: wgpu::BindGroup,
     ellipses_pipeline: wgpu::RenderPipeline,
-
-    layers: Vec<Layer>,
+    ellipses_wireframe_pipeline: Option<wgpu::RenderPipeline>,
+
+    rounded_rects_buffer: wgpu::Buffer,
+    rounded_rects_bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    rounded_rects_bind_group: wgpu::BindGroup,
+    rounded_rects_pipeline: wgpu::RenderPipeline,
+    rounded_rects_wireframe_pipeline: Option<wgpu::RenderPipeline>,
+
+    arcs_buffer: wgpu::Buffer,
+    arcs_bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    arcs_bind_group: wgpu::BindGroup,
+    arcs_pipeline: wgpu::RenderPipeline,
+    arcs_wireframe_pipeline: Option<wgpu::RenderPipeline>,
+
+    triangles_buffer: wgpu::Buffer,
+    triangles_bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    triangles_bind_group: wgpu::BindGroup,
+    triangles_pipeline: wgpu::RenderPipeline,
+    triangles_wireframe_pipeline: Option<wgpu::RenderPipeline>,
+
+    layers: Vec<LayerBatch>,
+    clip_rects: Vec<Rect>,
+    layer_instances: Vec<Range<u32>>,
+
+    wireframe: bool,
+    time: f32,
 }
 
 impl Ui {
-    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
-        let texture_bind_group_layout = texture::bind_group_layout(device);
+    pub fn new(
+        device: &wgpu::Device,
+        bind_group_layout_cache: &BindGroupLayoutCache,
+        texture_uploads: &mut TextureUploadQueue,
+    ) -> Self {
+        let texture_bind_group_layout = texture::bind_group_layout(
+            device,
+            bind_group_layout_cache,
+            wgpu::TextureFormat::Rgba8Unorm,
+        );
         let white_pixel_texture = Texture::new(
             device,
+            bind_group_layout_cache,
             "White Pixel Texture",
             1,
             1,
+            wgpu::TextureFormat::Rgba8Unorm,
             wgpu::TextureUsages::COPY_DST,
             wgpu::FilterMode::Nearest,
+            wgpu::AddressMode::Repeat,
         );
-        {
-            let texture = white_pixel_texture.texture_view().texture();
-            queue.write_texture(
-                texture.as_image_copy(),
-                bytemuck::cast_slice::<f32, _>(&[1.0, 1.0, 1.0, 1.0]),
-                wgpu::TexelCopyBufferLayout {
-                    offset: 0,
-                    bytes_per_row: Some(4 * 4),
-                    rows_per_image: None,
-                },
-                texture.size(),
-            );
-        }
+        texture_uploads.push(&white_pixel_texture, vec![1.0, 1.0, 1.0, 1.0]);
+
+        let supports_wireframe = device
+            .features()
+            .contains(wgpu::Features::POLYGON_MODE_LINE);
 
         let camera_buffer = camera_buffer(device);
-        let camera_bind_group_layout = camera_bind_group_layout(device);
+        let camera_bind_group_layout = camera_bind_group_layout(device, bind_group_layout_cache);
         let camera_bind_group =
             camera_bind_group(device, &camera_bind_group_layout, &camera_buffer);
 
         let lines_buffer = lines_buffer(device, 0);
-        let lines_bind_group_layout = lines_bind_group_layout(device);
+        let lines_bind_group_layout = lines_bind_group_layout(device, bind_group_layout_cache);
         let lines_bind_group = lines_bind_group(device, &lines_bind_group_layout, &lines_buffer);
 
         let lines_shader = device.create_shader_module(wgpu::include_wgsl!(concat!(
@@ -106,10 +276,21 @@ impl Ui {
             &lines_pipeline_layout,
             &lines_shader,
             wgpu::PrimitiveTopology::TriangleStrip,
+            wgpu::PolygonMode::Fill,
         );
+        let lines_wireframe_pipeline = supports_wireframe.then(|| {
+            render_pipeline(
+                device,
+                "Lines Wireframe Render Pipeline",
+                &lines_pipeline_layout,
+                &lines_shader,
+                wgpu::PrimitiveTopology::TriangleStrip,
+                wgpu::PolygonMode::Line,
+            )
+        });
 
         let quads_buffer = quads_buffer(device, 0);
-        let quads_bind_group_layout = quads_bind_group_layout(device);
+        let quads_bind_group_layout = quads_bind_group_layout(device, bind_group_layout_cache);
         let quads_bind_group = quads_bind_group(device, &quads_bind_group_layout, &quads_buffer);
 
         let quads_shader = device.create_shader_module(wgpu::include_wgsl!(concat!(
@@ -132,10 +313,22 @@ impl Ui {
             &quads_pipeline_layout,
             &quads_shader,
             wgpu::PrimitiveTopology::TriangleStrip,
+            wgpu::PolygonMode::Fill,
         );
+        let quads_wireframe_pipeline = supports_wireframe.then(|| {
+            render_pipeline(
+                device,
+                "Quads Wireframe Render Pipeline",
+                &quads_pipeline_layout,
+                &quads_shader,
+                wgpu::PrimitiveTopology::TriangleStrip,
+                wgpu::PolygonMode::Line,
+            )
+        });
 
         let ellipses_buffer = ellipses_buffer(device, 0);
-        let ellipses_bind_group_layout = ellipses_bind_group_layout(device);
+        let ellipses_bind_group_layout =
+            ellipses_bind_group_layout(device, bind_group_layout_cache);
         let ellipses_bind_group =
             ellipses_bind_group(device, &ellipses_bind_group_layout, &ellipses_buffer);
 
@@ -159,8 +352,128 @@ impl Ui {
             &ellipses_pipeline_layout,
             &ellipses_shader,
             wgpu::PrimitiveTopology::TriangleStrip,
+            wgpu::PolygonMode::Fill,
+        );
+        let ellipses_wireframe_pipeline = supports_wireframe.then(|| {
+            render_pipeline(
+                device,
+                "Ellipses Wireframe Render Pipeline",
+                &ellipses_pipeline_layout,
+                &ellipses_shader,
+                wgpu::PrimitiveTopology::TriangleStrip,
+                wgpu::PolygonMode::Line,
+            )
+        });
+
+        let rounded_rects_buffer = rounded_rects_buffer(device, 0);
+        let rounded_rects_bind_group_layout =
+            rounded_rects_bind_group_layout(device, bind_group_layout_cache);
+        let rounded_rects_bind_group = rounded_rects_bind_group(
+            device,
+            &rounded_rects_bind_group_layout,
+            &rounded_rects_buffer,
         );
 
+        let rounded_rects_shader = device.create_shader_module(wgpu::include_wgsl!(concat!(
+            env!("OUT_DIR"),
+            "/shaders/rounded_rects.wgsl"
+        )));
+        let rounded_rects_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Rounded Rects Render Pipeline Layout"),
+                bind_group_layouts: &[
+                    &camera_bind_group_layout,
+                    &rounded_rects_bind_group_layout,
+                    &texture_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+        let rounded_rects_pipeline = render_pipeline(
+            device,
+            "Rounded Rects Render Pipeline",
+            &rounded_rects_pipeline_layout,
+            &rounded_rects_shader,
+            wgpu::PrimitiveTopology::TriangleStrip,
+            wgpu::PolygonMode::Fill,
+        );
+        let rounded_rects_wireframe_pipeline = supports_wireframe.then(|| {
+            render_pipeline(
+                device,
+                "Rounded Rects Wireframe Render Pipeline",
+                &rounded_rects_pipeline_layout,
+                &rounded_rects_shader,
+                wgpu::PrimitiveTopology::TriangleStrip,
+                wgpu::PolygonMode::Line,
+            )
+        });
+
+        let arcs_buffer = arcs_buffer(device, 0);
+        let arcs_bind_group_layout = arcs_bind_group_layout(device, bind_group_layout_cache);
+        let arcs_bind_group = arcs_bind_group(device, &arcs_bind_group_layout, &arcs_buffer);
+
+        let arcs_shader = device.create_shader_module(wgpu::include_wgsl!(concat!(
+            env!("OUT_DIR"),
+            "/shaders/arcs.wgsl"
+        )));
+        let arcs_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Arcs Render Pipeline Layout"),
+            bind_group_layouts: &[&camera_bind_group_layout, &arcs_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let arcs_pipeline = render_pipeline(
+            device,
+            "Arcs Render Pipeline",
+            &arcs_pipeline_layout,
+            &arcs_shader,
+            wgpu::PrimitiveTopology::TriangleStrip,
+            wgpu::PolygonMode::Fill,
+        );
+        let arcs_wireframe_pipeline = supports_wireframe.then(|| {
+            render_pipeline(
+                device,
+                "Arcs Wireframe Render Pipeline",
+                &arcs_pipeline_layout,
+                &arcs_shader,
+                wgpu::PrimitiveTopology::TriangleStrip,
+                wgpu::PolygonMode::Line,
+            )
+        });
+
+        let triangles_buffer = triangles_buffer(device, 0);
+        let triangles_bind_group_layout =
+            triangles_bind_group_layout(device, bind_group_layout_cache);
+        let triangles_bind_group =
+            triangles_bind_group(device, &triangles_bind_group_layout, &triangles_buffer);
+
+        let triangles_shader = device.create_shader_module(wgpu::include_wgsl!(concat!(
+            env!("OUT_DIR"),
+            "/shaders/triangles.wgsl"
+        )));
+        let triangles_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Triangles Render Pipeline Layout"),
+                bind_group_layouts: &[&camera_bind_group_layout, &triangles_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let triangles_pipeline = render_pipeline(
+            device,
+            "Triangles Render Pipeline",
+            &triangles_pipeline_layout,
+            &triangles_shader,
+            wgpu::PrimitiveTopology::TriangleList,
+            wgpu::PolygonMode::Fill,
+        );
+        let triangles_wireframe_pipeline = supports_wireframe.then(|| {
+            render_pipeline(
+                device,
+                "Triangles Wireframe Render Pipeline",
+                &triangles_pipeline_layout,
+                &triangles_shader,
+                wgpu::PrimitiveTopology::TriangleList,
+                wgpu::PolygonMode::Line,
+            )
+        });
+
         Self {
             white_pixel_texture,
 
@@ -171,92 +484,324 @@ impl Ui {
             lines_bind_group_layout,
             lines_bind_group,
             lines_pipeline,
+            lines_wireframe_pipeline,
 
             quads_buffer,
             quads_bind_group_layout,
             quads_bind_group,
             quads_pipeline,
+            quads_wireframe_pipeline,
 
             ellipses_buffer,
             ellipses_bind_group_layout,
             ellipses_bind_group,
             ellipses_pipeline,
+            ellipses_wireframe_pipeline,
+
+            rounded_rects_buffer,
+            rounded_rects_bind_group_layout,
+            rounded_rects_bind_group,
+            rounded_rects_pipeline,
+            rounded_rects_wireframe_pipeline,
+
+            arcs_buffer,
+            arcs_bind_group_layout,
+            arcs_bind_group,
+            arcs_pipeline,
+            arcs_wireframe_pipeline,
+
+            triangles_buffer,
+            triangles_bind_group_layout,
+            triangles_bind_group,
+            triangles_pipeline,
+            triangles_wireframe_pipeline,
 
             layers: vec![],
+            clip_rects: vec![],
+            layer_instances: vec![],
+
+            wireframe: false,
+            time: 0.0,
         }
     }
 
+    pub fn supports_wireframe(&self) -> bool {
+        self.lines_wireframe_pipeline.is_some()
+    }
+
+    pub fn wireframe(&self) -> bool {
+        self.wireframe
+    }
+
+    // has no effect unless the device supports `wgpu::Features::POLYGON_MODE_LINE`
+    pub fn set_wireframe(&mut self, wireframe: bool) {
+        self.wireframe = wireframe;
+    }
+
+    pub fn set_time(&mut self, time_seconds: f32) {
+        self.time = time_seconds;
+    }
+
     pub fn clear(&mut self) {
         self.layers.clear();
+        self.clip_rects.clear();
+    }
+
+    // everything pushed until the matching pop_clip_rect is clipped to `rect`, and any outer clip rects
+    pub fn push_clip_rect(&mut self, rect: Rect) {
+        let rect = match self.clip_rects.last() {
+            Some(outer) => rect.intersection(outer),
+            None => rect,
+        };
+        self.clip_rects.push(rect);
+    }
+
+    pub fn pop_clip_rect(&mut self) {
+        self.clip_rects
+            .pop()
+            .expect("pop_clip_rect should match a push_clip_rect");
+    }
+
+    // the last layer, if new elements at `z` can be added to it
+    fn last_layer(&mut self, z: f32) -> Option<&mut Layer> {
+        let clip = self.clip_rects.last().copied();
+        self.layers
+            .last_mut()
+            .filter(|batch| batch.clip == clip && batch.z == z)
+            .map(|batch| &mut batch.layer)
+    }
+
+    fn push_layer(&mut self, layer: Layer, z: f32) {
+        let clip = self.clip_rects.last().copied();
+        self.layers.push(LayerBatch { layer, clip, z });
     }
 
     pub fn push_line(&mut self, line: Line) {
+        self.push_line_z(line, 0.0);
+    }
+
+    // elements with a higher z are drawn on top, elements with the same z are drawn in push order
+    pub fn push_line_z(&mut self, line: Line, z: f32) {
         let Line { a, b, color, width } = line;
+        let (width, width_mode) = match width {
+            LineWidth::Ui(width) => (width, 0),
+            LineWidth::ScreenPixels(width) => (width, 1),
+        };
         let gpu_line = GpuLine {
             a: a.into(),
             b: b.into(),
             color: color.into(),
             width,
+            width_mode,
+            _padding: [0; 3],
         };
 
-        if let Some(Layer::Lines { gpu_lines }) = self.layers.last_mut() {
+        if let Some(Layer::Lines { gpu_lines }) = self.last_layer(z) {
             gpu_lines.push(gpu_line);
         } else {
-            self.layers.push(Layer::Lines {
-                gpu_lines: vec![gpu_line],
-            });
+            self.push_layer(
+                Layer::Lines {
+                    gpu_lines: vec![gpu_line],
+                },
+                z,
+            );
         }
     }
 
     pub fn push_quad(&mut self, quad: Quad, texture: Option<TextureInfo>) {
+        self.push_quad_z(quad, texture, 0.0);
+    }
+
+    pub fn push_quad_z(&mut self, quad: Quad, texture: Option<TextureInfo>, z: f32) {
         let TextureInfo {
             texture,
             uv_offset,
             uv_size,
-        } = texture.unwrap_or_else(|| TextureInfo {
-            texture: self.white_pixel_texture.clone(),
-            uv_offset: cgmath::vec2(0.0, 0.0),
-            uv_size: cgmath::vec2(1.0, 1.0),
-        });
+            uv_rotation,
+            flip_x,
+            flip_y,
+            uv_tile_scale,
+        } = texture.unwrap_or_else(|| TextureInfo::whole(self.white_pixel_texture.clone()));
+        let uv_flip = gpu_uv_flip(flip_x, flip_y);
+        let uv_tile_scale =
+            if uv_offset == cgmath::vec2(0.0, 0.0) && uv_size == cgmath::vec2(1.0, 1.0) {
+                uv_tile_scale
+            } else {
+                cgmath::vec2(1.0, 1.0)
+            };
 
         let Quad {
             position,
             size,
             color,
+            gradient,
+            pulse_phase,
         } = quad;
+        let QuadGradient {
+            color: color_b,
+            angle: gradient_angle,
+        } = gradient.unwrap_or(QuadGradient { color, angle: 0.0 });
         let gpu_quad = GpuQuad {
             position: position.into(),
             size: size.into(),
             color: color.into(),
             uv_offset: uv_offset.into(),
             uv_size: uv_size.into(),
+            uv_tile_scale: uv_tile_scale.into(),
+            gradient_angle,
+            uv_rotation,
+            color_b: color_b.into(),
+            uv_flip,
+            animated: pulse_phase.is_some() as u32,
+            animation_phase: pulse_phase.unwrap_or(0.0),
+            _padding: 0,
         };
 
         if let Some(Layer::Quads {
             gpu_quads,
             texture: last_texture,
-        }) = self.layers.last_mut()
+        }) = self.last_layer(z)
             && texture == *last_texture
         {
             gpu_quads.push(gpu_quad);
         } else {
-            self.layers.push(Layer::Quads {
-                gpu_quads: vec![gpu_quad],
-                texture,
-            });
+            self.push_layer(
+                Layer::Quads {
+                    gpu_quads: vec![gpu_quad],
+                    texture,
+                },
+                z,
+            );
+        }
+    }
+
+    pub fn push_quad_border(&mut self, border: QuadBorder) {
+        self.push_quad_border_z(border, 0.0);
+    }
+
+    pub fn push_quad_border_z(&mut self, border: QuadBorder, z: f32) {
+        let QuadBorder {
+            position,
+            size,
+            color,
+            thickness,
+        } = border;
+
+        // the stroke sits inside the rect, top and bottom span the full width
+        // so the sides only need to cover the remaining height
+        let thickness = thickness.min(size.x * 0.5).min(size.y * 0.5);
+        let half_size = size * 0.5;
+        let side_height = size.y - thickness * 2.0;
+        let edges = [
+            (
+                cgmath::vec2(0.0, half_size.y - thickness * 0.5),
+                cgmath::vec2(size.x, thickness),
+            ),
+            (
+                cgmath::vec2(0.0, -half_size.y + thickness * 0.5),
+                cgmath::vec2(size.x, thickness),
+            ),
+            (
+                cgmath::vec2(-half_size.x + thickness * 0.5, 0.0),
+                cgmath::vec2(thickness, side_height),
+            ),
+            (
+                cgmath::vec2(half_size.x - thickness * 0.5, 0.0),
+                cgmath::vec2(thickness, side_height),
+            ),
+        ];
+        for (offset, size) in edges {
+            self.push_quad_z(
+                Quad {
+                    position: position + offset,
+                    size,
+                    color,
+                    gradient: None,
+                    pulse_phase: None,
+                },
+                None,
+                z,
+            );
+        }
+    }
+
+    pub fn push_nine_slice(&mut self, nine_slice: NineSlice, texture: TextureInfo) {
+        self.push_nine_slice_z(nine_slice, texture, 0.0);
+    }
+
+    pub fn push_nine_slice_z(&mut self, nine_slice: NineSlice, texture: TextureInfo, z: f32) {
+        let NineSlice {
+            position,
+            size,
+            color,
+            border,
+            texture_border,
+        } = nine_slice;
+        let TextureInfo {
+            texture,
+            uv_offset,
+            uv_size,
+            ..
+        } = texture;
+
+        let texture_size = texture.texture_view().texture().size();
+        let uv_border = cgmath::vec2(
+            (texture_border / texture_size.width as f32).min(uv_size.x.abs() * 0.5),
+            (texture_border / texture_size.height as f32).min(uv_size.y.abs() * 0.5),
+        );
+        let border = border.min(size.x * 0.5).min(size.y * 0.5);
+
+        // (start, length) of each column and row, relative to the min corner
+        let slices = |size: f32, border: f32| {
+            [
+                (0.0, border),
+                (border, size - border * 2.0),
+                (size - border, border),
+            ]
+        };
+        let columns = slices(size.x, border)
+            .into_iter()
+            .zip(slices(uv_size.x, uv_border.x));
+        for (x, uv_x) in columns {
+            let rows = slices(size.y, border)
+                .into_iter()
+                .zip(slices(uv_size.y, uv_border.y));
+            for (y, uv_y) in rows {
+                let slice_size = cgmath::vec2(x.1, y.1);
+                self.push_quad_z(
+                    Quad {
+                        position: position - size * 0.5 + cgmath::vec2(x.0, y.0) + slice_size * 0.5,
+                        size: slice_size,
+                        color,
+                        gradient: None,
+                        pulse_phase: None,
+                    },
+                    Some(TextureInfo {
+                        uv_offset: uv_offset + cgmath::vec2(uv_x.0, uv_y.0),
+                        uv_size: cgmath::vec2(uv_x.1, uv_y.1),
+                        ..TextureInfo::whole(texture.clone())
+                    }),
+                    z,
+                );
+            }
         }
     }
 
     pub fn push_ellipse(&mut self, ellipse: Ellipse, texture: Option<TextureInfo>) {
+        self.push_ellipse_z(ellipse, texture, 0.0);
+    }
+
+    pub fn push_ellipse_z(&mut self, ellipse: Ellipse, texture: Option<TextureInfo>, z: f32) {
         let TextureInfo {
             texture,
             uv_offset,
             uv_size,
-        } = texture.unwrap_or_else(|| TextureInfo {
-            texture: self.white_pixel_texture.clone(),
-            uv_offset: cgmath::vec2(0.0, 0.0),
-            uv_size: cgmath::vec2(1.0, 1.0),
-        });
+            uv_rotation,
+            flip_x,
+            flip_y,
+            ..
+        } = texture.unwrap_or_else(|| TextureInfo::whole(self.white_pixel_texture.clone()));
+        let uv_flip = gpu_uv_flip(flip_x, flip_y);
 
         let Ellipse {
             position,
@@ -269,20 +814,144 @@ impl Ui {
             color: color.into(),
             uv_offset: uv_offset.into(),
             uv_size: uv_size.into(),
+            uv_rotation,
+            uv_flip,
+            _padding: [0; 2],
         };
 
         if let Some(Layer::Ellipses {
             gpu_ellipses,
             texture: last_texture,
-        }) = self.layers.last_mut()
+        }) = self.last_layer(z)
             && texture == *last_texture
         {
             gpu_ellipses.push(gpu_ellipse);
         } else {
-            self.layers.push(Layer::Ellipses {
-                gpu_ellipses: vec![gpu_ellipse],
-                texture,
-            });
+            self.push_layer(
+                Layer::Ellipses {
+                    gpu_ellipses: vec![gpu_ellipse],
+                    texture,
+                },
+                z,
+            );
+        }
+    }
+
+    pub fn push_rounded_rect(&mut self, rounded_rect: RoundedRect, texture: Option<TextureInfo>) {
+        self.push_rounded_rect_z(rounded_rect, texture, 0.0);
+    }
+
+    pub fn push_rounded_rect_z(
+        &mut self,
+        rounded_rect: RoundedRect,
+        texture: Option<TextureInfo>,
+        z: f32,
+    ) {
+        let TextureInfo {
+            texture,
+            uv_offset,
+            uv_size,
+            uv_rotation,
+            flip_x,
+            flip_y,
+            ..
+        } = texture.unwrap_or_else(|| TextureInfo::whole(self.white_pixel_texture.clone()));
+        let uv_flip = gpu_uv_flip(flip_x, flip_y);
+
+        let RoundedRect {
+            position,
+            size,
+            corner_radius,
+            color,
+        } = rounded_rect;
+        let gpu_rounded_rect = GpuRoundedRect {
+            position: position.into(),
+            size: size.into(),
+            color: color.into(),
+            uv_offset: uv_offset.into(),
+            uv_size: uv_size.into(),
+            corner_radius,
+            uv_rotation,
+            uv_flip,
+            _padding: 0,
+        };
+
+        if let Some(Layer::RoundedRects {
+            gpu_rounded_rects,
+            texture: last_texture,
+        }) = self.last_layer(z)
+            && texture == *last_texture
+        {
+            gpu_rounded_rects.push(gpu_rounded_rect);
+        } else {
+            self.push_layer(
+                Layer::RoundedRects {
+                    gpu_rounded_rects: vec![gpu_rounded_rect],
+                    texture,
+                },
+                z,
+            );
+        }
+    }
+
+    pub fn push_arc(&mut self, arc: EllipseArc) {
+        self.push_arc_z(arc, 0.0);
+    }
+
+    pub fn push_arc_z(&mut self, arc: EllipseArc, z: f32) {
+        let EllipseArc {
+            position,
+            radius,
+            start_angle,
+            end_angle,
+            color,
+            width,
+        } = arc;
+        let gpu_arc = GpuArc {
+            position: position.into(),
+            radius: radius.into(),
+            color: color.into(),
+            start_angle,
+            end_angle,
+            width,
+            _padding: 0.0,
+        };
+
+        if let Some(Layer::Arcs { gpu_arcs }) = self.last_layer(z) {
+            gpu_arcs.push(gpu_arc);
+        } else {
+            self.push_layer(
+                Layer::Arcs {
+                    gpu_arcs: vec![gpu_arc],
+                },
+                z,
+            );
+        }
+    }
+
+    pub fn push_triangle(&mut self, triangle: Triangle) {
+        self.push_triangle_z(triangle, 0.0);
+    }
+
+    pub fn push_triangle_z(&mut self, triangle: Triangle, z: f32) {
+        let Triangle { a, b, c, color } = triangle;
+        let gpu_triangle = GpuTriangle {
+            color: color.into(),
+            a: a.into(),
+            b: b.into(),
+            c: c.into(),
+            _padding: [0.0; 2],
+        };
+
+        if let Some(Layer::Triangles { gpu_triangles }) = self.last_layer(z) {
+            gpu_triangles.push(gpu_triangle);
+        } else {
+            self.push_layer(
+                Layer::Triangles {
+                    gpu_triangles: vec![gpu_triangle],
+                },
+                z,
+            );
         }
     }
 
@@ -297,14 +966,22 @@ impl Ui {
         {
             let gpu_camera = GpuCamera {
                 aspect: width as f32 / height as f32,
+                pixel_size: 2.0 / height as f32,
+                time: self.time,
             };
             queue.write_buffer(&self.camera_buffer, 0, bytemuck::bytes_of(&gpu_camera));
         }
 
+        // a stable sort, so layers with the same z keep their push order
+        self.layers.sort_by(|a, b| a.z.total_cmp(&b.z));
+
         let mut required_lines_count = 0;
         let mut required_quads_count = 0;
         let mut required_ellipses_count = 0;
-        for layer in &self.layers {
+        let mut required_rounded_rects_count = 0;
+        let mut required_arcs_count = 0;
+        let mut required_triangles_count = 0;
+        for LayerBatch { layer, .. } in &self.layers {
             match layer {
                 Layer::Lines { gpu_lines, .. } => {
                     required_lines_count += gpu_lines.len();
@@ -315,6 +992,17 @@ impl Ui {
                 Layer::Ellipses { gpu_ellipses, .. } => {
                     required_ellipses_count += gpu_ellipses.len();
                 }
+                Layer::RoundedRects {
+                    gpu_rounded_rects, ..
+                } => {
+                    required_rounded_rects_count += gpu_rounded_rects.len();
+                }
+                Layer::Arcs { gpu_arcs, .. } => {
+                    required_arcs_count += gpu_arcs.len();
+                }
+                Layer::Triangles { gpu_triangles, .. } => {
+                    required_triangles_count += gpu_triangles.len();
+                }
             }
         }
 
@@ -337,16 +1025,36 @@ impl Ui {
             );
         }
 
-        struct GpuLayer<'a> {
-            pipeline: &'a wgpu::RenderPipeline,
-            texture: Option<&'a Texture>,
-            bind_group: &'a wgpu::BindGroup,
-            vertex_count: u32,
-            instance_start: u32,
-            instance_end: u32,
+        if required_rounded_rects_count * size_of::<GpuRoundedRect>()
+            > self.rounded_rects_buffer.size() as _
+        {
+            self.rounded_rects_buffer = rounded_rects_buffer(device, required_rounded_rects_count);
+            self.rounded_rects_bind_group = rounded_rects_bind_group(
+                device,
+                &self.rounded_rects_bind_group_layout,
+                &self.rounded_rects_buffer,
+            );
+        }
+
+        if required_arcs_count * size_of::<GpuArc>() > self.arcs_buffer.size() as _ {
+            self.arcs_buffer = arcs_buffer(device, required_arcs_count);
+            self.arcs_bind_group =
+                arcs_bind_group(device, &self.arcs_bind_group_layout, &self.arcs_buffer);
+        }
+
+        if required_triangles_count * size_of::<GpuTriangle>() > self.triangles_buffer.size() as _ {
+            self.triangles_buffer = triangles_buffer(device, required_triangles_count);
+            self.triangles_bind_group = triangles_bind_group(
+                device,
+                &self.triangles_bind_group_layout,
+                &self.triangles_buffer,
+            );
         }
 
-        let layers = {
+        // the instance range of each layer, reused between frames to avoid allocating
+        let mut layer_instances = std::mem::take(&mut self.layer_instances);
+        layer_instances.clear();
+        {
             let mut lines_buffer =
                 NonZeroU64::new((required_lines_count * size_of::<GpuLine>()) as _)
                     .and_then(|length| queue.write_buffer_with(&self.lines_buffer, 0, length));
@@ -362,12 +1070,30 @@ impl Ui {
                     .and_then(|length| queue.write_buffer_with(&self.ellipses_buffer, 0, length));
             let mut ellipses_buffer = ellipses_buffer.as_deref_mut();
 
+            let mut rounded_rects_buffer =
+                NonZeroU64::new((required_rounded_rects_count * size_of::<GpuRoundedRect>()) as _)
+                    .and_then(|length| {
+                        queue.write_buffer_with(&self.rounded_rects_buffer, 0, length)
+                    });
+            let mut rounded_rects_buffer = rounded_rects_buffer.as_deref_mut();
+
+            let mut arcs_buffer = NonZeroU64::new((required_arcs_count * size_of::<GpuArc>()) as _)
+                .and_then(|length| queue.write_buffer_with(&self.arcs_buffer, 0, length));
+            let mut arcs_buffer = arcs_buffer.as_deref_mut();
+
+            let mut triangles_buffer =
+                NonZeroU64::new((required_triangles_count * size_of::<GpuTriangle>()) as _)
+                    .and_then(|length| queue.write_buffer_with(&self.triangles_buffer, 0, length));
+            let mut triangles_buffer = triangles_buffer.as_deref_mut();
+
             let mut lines_so_far = 0usize;
             let mut quads_so_far = 0usize;
             let mut ellipses_so_far = 0usize;
-            self.layers
-                .iter()
-                .map(|layer| match layer {
+            let mut rounded_rects_so_far = 0usize;
+            let mut arcs_so_far = 0usize;
+            let mut triangles_so_far = 0usize;
+            for LayerBatch { layer, .. } in &self.layers {
+                let instances = match layer {
                     Layer::Lines { gpu_lines } => {
                         let lines_buffer = lines_buffer.as_deref_mut().unwrap_or_default();
 
@@ -375,92 +1101,204 @@ impl Ui {
                         lines_buffer[lines_so_far * size_of::<GpuLine>()..][..size]
                             .copy_from_slice(bytemuck::cast_slice(gpu_lines));
 
-                        let layer = GpuLayer {
-                            pipeline: &self.lines_pipeline,
-                            bind_group: &self.lines_bind_group,
-                            texture: None,
-                            vertex_count: 4,
-                            instance_start: lines_so_far as _,
-                            instance_end: (lines_so_far + gpu_lines.len()).try_into().expect(
+                        let instances = lines_so_far as _
+                            ..(lines_so_far + gpu_lines.len()).try_into().expect(
                                 "the number of lines in a layer should be less than u32::MAX",
-                            ),
-                        };
+                            );
 
                         lines_so_far += gpu_lines.len();
 
-                        layer
+                        instances
                     }
 
-                    Layer::Quads { gpu_quads, texture } => {
+                    Layer::Quads { gpu_quads, .. } => {
                         let quads_buffer = quads_buffer.as_deref_mut().unwrap_or_default();
 
                         let size = size_of_val::<[_]>(gpu_quads);
                         quads_buffer[quads_so_far * size_of::<GpuQuad>()..][..size]
                             .copy_from_slice(bytemuck::cast_slice(gpu_quads));
 
-                        let layer = GpuLayer {
-                            pipeline: &self.quads_pipeline,
-                            bind_group: &self.quads_bind_group,
-                            texture: Some(texture),
-                            vertex_count: 4,
-                            instance_start: quads_so_far as _,
-                            instance_end: (quads_so_far + gpu_quads.len()).try_into().expect(
+                        let instances = quads_so_far as _
+                            ..(quads_so_far + gpu_quads.len()).try_into().expect(
                                 "the number of quads in a layer should be less than u32::MAX",
-                            ),
-                        };
+                            );
 
                         quads_so_far += gpu_quads.len();
 
-                        layer
+                        instances
                     }
 
-                    Layer::Ellipses {
-                        gpu_ellipses,
-                        texture,
-                    } => {
+                    Layer::Ellipses { gpu_ellipses, .. } => {
                         let ellipses_buffer = ellipses_buffer.as_deref_mut().unwrap_or_default();
 
                         let size = size_of_val::<[_]>(gpu_ellipses);
                         ellipses_buffer[ellipses_so_far * size_of::<GpuEllipse>()..][..size]
                             .copy_from_slice(bytemuck::cast_slice(gpu_ellipses));
 
-                        let layer = GpuLayer {
-                            pipeline: &self.ellipses_pipeline,
-                            bind_group: &self.ellipses_bind_group,
-                            texture: Some(texture),
-                            vertex_count: 4,
-                            instance_start: ellipses_so_far as _,
-                            instance_end: (ellipses_so_far + gpu_ellipses.len()).try_into().expect(
+                        let instances = ellipses_so_far as _
+                            ..(ellipses_so_far + gpu_ellipses.len()).try_into().expect(
                                 "the number of ellipses in a layer should be less than u32::MAX",
-                            ),
-                        };
+                            );
 
                         ellipses_so_far += gpu_ellipses.len();
 
-                        layer
+                        instances
                     }
-                })
-                .collect::<Vec<_>>()
-        };
 
-        for GpuLayer {
-            pipeline,
-            bind_group,
-            texture,
-            vertex_count,
-            instance_start,
-            instance_end,
-        } in layers
+                    Layer::RoundedRects {
+                        gpu_rounded_rects, ..
+                    } => {
+                        let rounded_rects_buffer =
+                            rounded_rects_buffer.as_deref_mut().unwrap_or_default();
+
+                        let size = size_of_val::<[_]>(gpu_rounded_rects);
+                        rounded_rects_buffer[rounded_rects_so_far * size_of::<GpuRoundedRect>()..]
+                            [..size]
+                            .copy_from_slice(bytemuck::cast_slice(gpu_rounded_rects));
+
+                        let instances = rounded_rects_so_far as _
+                            ..(rounded_rects_so_far + gpu_rounded_rects.len()).try_into().expect(
+                                "the number of rounded rects in a layer should be less than u32::MAX",
+                            );
+
+                        rounded_rects_so_far += gpu_rounded_rects.len();
+
+                        instances
+                    }
+
+                    Layer::Arcs { gpu_arcs, .. } => {
+                        let arcs_buffer = arcs_buffer.as_deref_mut().unwrap_or_default();
+
+                        let size = size_of_val::<[_]>(gpu_arcs);
+                        arcs_buffer[arcs_so_far * size_of::<GpuArc>()..][..size]
+                            .copy_from_slice(bytemuck::cast_slice(gpu_arcs));
+
+                        let instances = arcs_so_far as _
+                            ..(arcs_so_far + gpu_arcs.len()).try_into().expect(
+                                "the number of arcs in a layer should be less than u32::MAX",
+                            );
+
+                        arcs_so_far += gpu_arcs.len();
+
+                        instances
+                    }
+
+                    Layer::Triangles { gpu_triangles, .. } => {
+                        let triangles_buffer = triangles_buffer.as_deref_mut().unwrap_or_default();
+
+                        let size = size_of_val::<[_]>(gpu_triangles);
+                        triangles_buffer[triangles_so_far * size_of::<GpuTriangle>()..][..size]
+                            .copy_from_slice(bytemuck::cast_slice(gpu_triangles));
+
+                        let instances = triangles_so_far as _
+                            ..(triangles_so_far + gpu_triangles.len()).try_into().expect(
+                                "the number of triangles in a layer should be less than u32::MAX",
+                            );
+
+                        triangles_so_far += gpu_triangles.len();
+
+                        instances
+                    }
+                };
+                layer_instances.push(instances);
+            }
+        }
+
+        let mut current_clip = None;
+        for (LayerBatch { layer, clip, .. }, instances) in self.layers.iter().zip(&layer_instances)
         {
+            if *clip != current_clip {
+                let (x, y, clip_width, clip_height) = match clip {
+                    Some(clip) => scissor_rect(clip, width, height),
+                    None => (0, 0, width, height),
+                };
+                render_pass.set_scissor_rect(x, y, clip_width, clip_height);
+                current_clip = *clip;
+            }
+
+            let (pipeline, wireframe_pipeline, bind_group, texture, vertices) = match layer {
+                Layer::Lines { .. } => (
+                    &self.lines_pipeline,
+                    &self.lines_wireframe_pipeline,
+                    &self.lines_bind_group,
+                    None,
+                    0..4,
+                ),
+                Layer::Quads { texture, .. } => (
+                    &self.quads_pipeline,
+                    &self.quads_wireframe_pipeline,
+                    &self.quads_bind_group,
+                    Some(texture),
+                    0..4,
+                ),
+                Layer::Ellipses { texture, .. } => (
+                    &self.ellipses_pipeline,
+                    &self.ellipses_wireframe_pipeline,
+                    &self.ellipses_bind_group,
+                    Some(texture),
+                    0..4,
+                ),
+                Layer::RoundedRects { texture, .. } => (
+                    &self.rounded_rects_pipeline,
+                    &self.rounded_rects_wireframe_pipeline,
+                    &self.rounded_rects_bind_group,
+                    Some(texture),
+                    0..4,
+                ),
+                Layer::Arcs { .. } => (
+                    &self.arcs_pipeline,
+                    &self.arcs_wireframe_pipeline,
+                    &self.arcs_bind_group,
+                    None,
+                    0..4,
+                ),
+                Layer::Triangles { .. } => (
+                    &self.triangles_pipeline,
+                    &self.triangles_wireframe_pipeline,
+                    &self.triangles_bind_group,
+                    None,
+                    0..3,
+                ),
+            };
+            let pipeline = match wireframe_pipeline {
+                Some(wireframe_pipeline) if self.wireframe => wireframe_pipeline,
+                _ => pipeline,
+            };
+
             render_pass.set_pipeline(pipeline);
             render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
             render_pass.set_bind_group(1, bind_group, &[]);
             render_pass.set_bind_group(2, texture.map(Texture::bind_group), &[]);
-            render_pass.draw(0..vertex_count, instance_start..instance_end);
+            render_pass.draw(vertices, instances.clone());
+        }
+        if current_clip.is_some() {
+            render_pass.set_scissor_rect(0, 0, width, height);
         }
+        self.layer_instances = layer_instances;
     }
 }
 
+// converts from ui space, where y goes from -1 at the bottom to 1 at the top, to pixels from the top left
+fn scissor_rect(rect: &Rect, width: u32, height: u32) -> (u32, u32, u32, u32) {
+    let aspect = width as f32 / height as f32;
+    let to_pixels = |point: cgmath::Vector2<f32>| {
+        (
+            ((point.x / aspect + 1.0) * 0.5 * width as f32).clamp(0.0, width as f32) as u32,
+            ((1.0 - point.y) * 0.5 * height as f32).clamp(0.0, height as f32) as u32,
+        )
+    };
+    let (left, bottom) = to_pixels(rect.min());
+    let (right, top) = to_pixels(rect.max());
+    (left, top, right - left, bottom - top)
+}
+
+struct LayerBatch {
+    layer: Layer,
+    // the clip rect that was active when the layer was pushed
+    clip: Option<Rect>,
+    z: f32,
+}
+
 enum Layer {
     Lines {
         gpu_lines: Vec<GpuLine>,
@@ -473,12 +1311,24 @@ enum Layer {
         gpu_ellipses: Vec<GpuEllipse>,
         texture: Texture,
     },
+    RoundedRects {
+        gpu_rounded_rects: Vec<GpuRoundedRect>,
+        texture: Texture,
+    },
+    Arcs {
+        gpu_arcs: Vec<GpuArc>,
+    },
+    Triangles {
+        gpu_triangles: Vec<GpuTriangle>,
+    },
 }
 
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
 #[repr(C)]
 struct GpuCamera {
     pub aspect: f32,
+    pub pixel_size: f32,
+    pub time: f32,
 }
 
 fn camera_buffer(device: &wgpu::Device) -> wgpu::Buffer {
@@ -490,20 +1340,26 @@ fn camera_buffer(device: &wgpu::Device) -> wgpu::Buffer {
     })
 }
 
-fn camera_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
-    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        label: Some("Camera Bind Group Layout"),
-        entries: &[wgpu::BindGroupLayoutEntry {
-            binding: 0,
-            visibility: wgpu::ShaderStages::VERTEX,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            count: None,
-        }],
-    })
+fn camera_bind_group_layout(
+    device: &wgpu::Device,
+    bind_group_layout_cache: &BindGroupLayoutCache,
+) -> Arc<wgpu::BindGroupLayout> {
+    bind_group_layout_cache.get_or_create(
+        device,
+        &wgpu::BindGroupLayoutDescriptor {
+            label: Some("Camera Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        },
+    )
 }
 
 fn camera_bind_group(
@@ -528,6 +1384,8 @@ struct GpuLine {
     pub b: [f32; 2],
     pub color: [f32; 3],
     pub width: f32,
+    pub width_mode: u32,
+    pub _padding: [u32; 3],
 }
 
 fn lines_buffer(device: &wgpu::Device, length: usize) -> wgpu::Buffer {
@@ -541,20 +1399,26 @@ fn lines_buffer(device: &wgpu::Device, length: usize) -> wgpu::Buffer {
     })
 }
 
-fn lines_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
-    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        label: Some("Lines Bind Group Layout"),
-        entries: &[wgpu::BindGroupLayoutEntry {
-            binding: 0,
-            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Storage { read_only: true },
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            count: None,
-        }],
-    })
+fn lines_bind_group_layout(
+    device: &wgpu::Device,
+    bind_group_layout_cache: &BindGroupLayoutCache,
+) -> Arc<wgpu::BindGroupLayout> {
+    bind_group_layout_cache.get_or_create(
+        device,
+        &wgpu::BindGroupLayoutDescriptor {
+            label: Some("Lines Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        },
+    )
 }
 
 fn lines_bind_group(
@@ -572,6 +1436,10 @@ fn lines_bind_group(
     })
 }
 
+fn gpu_uv_flip(flip_x: bool, flip_y: bool) -> u32 {
+    (flip_x as u32) | ((flip_y as u32) << 1)
+}
+
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
 #[repr(C)]
 struct GpuQuad {
@@ -580,6 +1448,14 @@ struct GpuQuad {
     pub color: [f32; 4],
     pub uv_offset: [f32; 2],
     pub uv_size: [f32; 2],
+    pub uv_tile_scale: [f32; 2],
+    pub gradient_angle: f32,
+    pub uv_rotation: f32,
+    pub color_b: [f32; 4],
+    pub uv_flip: u32,
+    pub animated: u32,
+    pub animation_phase: f32,
+    pub _padding: u32,
 }
 
 fn quads_buffer(device: &wgpu::Device, length: usize) -> wgpu::Buffer {
@@ -593,20 +1469,26 @@ fn quads_buffer(device: &wgpu::Device, length: usize) -> wgpu::Buffer {
     })
 }
 
-fn quads_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
-    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        label: Some("Quads Bind Group Layout"),
-        entries: &[wgpu::BindGroupLayoutEntry {
-            binding: 0,
-            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Storage { read_only: true },
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            count: None,
-        }],
-    })
+fn quads_bind_group_layout(
+    device: &wgpu::Device,
+    bind_group_layout_cache: &BindGroupLayoutCache,
+) -> Arc<wgpu::BindGroupLayout> {
+    bind_group_layout_cache.get_or_create(
+        device,
+        &wgpu::BindGroupLayoutDescriptor {
+            label: Some("Quads Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        },
+    )
 }
 
 fn quads_bind_group(
@@ -632,6 +1514,9 @@ struct GpuEllipse {
     pub color: [f32; 4],
     pub uv_offset: [f32; 2],
     pub uv_size: [f32; 2],
+    pub uv_rotation: f32,
+    pub uv_flip: u32,
+    pub _padding: [u32; 2],
 }
 
 fn ellipses_buffer(device: &wgpu::Device, length: usize) -> wgpu::Buffer {
@@ -645,20 +1530,26 @@ fn ellipses_buffer(device: &wgpu::Device, length: usize) -> wgpu::Buffer {
     })
 }
 
-fn ellipses_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
-    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        label: Some("Ellipses Bind Group Layout"),
-        entries: &[wgpu::BindGroupLayoutEntry {
-            binding: 0,
-            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Storage { read_only: true },
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            count: None,
-        }],
-    })
+fn ellipses_bind_group_layout(
+    device: &wgpu::Device,
+    bind_group_layout_cache: &BindGroupLayoutCache,
+) -> Arc<wgpu::BindGroupLayout> {
+    bind_group_layout_cache.get_or_create(
+        device,
+        &wgpu::BindGroupLayoutDescriptor {
+            label: Some("Ellipses Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        },
+    )
 }
 
 fn ellipses_bind_group(
@@ -675,3 +1566,183 @@ fn ellipses_bind_group(
         }],
     })
 }
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+struct GpuRoundedRect {
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub color: [f32; 4],
+    pub uv_offset: [f32; 2],
+    pub uv_size: [f32; 2],
+    pub corner_radius: f32,
+    pub uv_rotation: f32,
+    pub uv_flip: u32,
+    pub _padding: u32,
+}
+
+fn rounded_rects_buffer(device: &wgpu::Device, length: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Rounded Rects Buffer"),
+        size: (length.max(1) * size_of::<GpuRoundedRect>())
+            .try_into()
+            .expect("the size of the rounded rects buffer should fit in a wgpu::BufferAddress"),
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn rounded_rects_bind_group_layout(
+    device: &wgpu::Device,
+    bind_group_layout_cache: &BindGroupLayoutCache,
+) -> Arc<wgpu::BindGroupLayout> {
+    bind_group_layout_cache.get_or_create(
+        device,
+        &wgpu::BindGroupLayoutDescriptor {
+            label: Some("Rounded Rects Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        },
+    )
+}
+
+fn rounded_rects_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    rounded_rects_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Rounded Rects Bind Group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: rounded_rects_buffer.as_entire_binding(),
+        }],
+    })
+}
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+struct GpuArc {
+    pub position: [f32; 2],
+    pub radius: [f32; 2],
+    pub color: [f32; 4],
+    pub start_angle: f32,
+    pub end_angle: f32,
+    pub width: f32,
+    pub _padding: f32,
+}
+
+fn arcs_buffer(device: &wgpu::Device, length: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Arcs Buffer"),
+        size: (length.max(1) * size_of::<GpuArc>())
+            .try_into()
+            .expect("the size of the arcs buffer should fit in a wgpu::BufferAddress"),
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn arcs_bind_group_layout(
+    device: &wgpu::Device,
+    bind_group_layout_cache: &BindGroupLayoutCache,
+) -> Arc<wgpu::BindGroupLayout> {
+    bind_group_layout_cache.get_or_create(
+        device,
+        &wgpu::BindGroupLayoutDescriptor {
+            label: Some("Arcs Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        },
+    )
+}
+
+fn arcs_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    arcs_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Arcs Bind Group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: arcs_buffer.as_entire_binding(),
+        }],
+    })
+}
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+struct GpuTriangle {
+    pub color: [f32; 4],
+    pub a: [f32; 2],
+    pub b: [f32; 2],
+    pub c: [f32; 2],
+    pub _padding: [f32; 2],
+}
+
+fn triangles_buffer(device: &wgpu::Device, length: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Triangles Buffer"),
+        size: (length.max(1) * size_of::<GpuTriangle>())
+            .try_into()
+            .expect("the size of the triangles buffer should fit in a wgpu::BufferAddress"),
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn triangles_bind_group_layout(
+    device: &wgpu::Device,
+    bind_group_layout_cache: &BindGroupLayoutCache,
+) -> Arc<wgpu::BindGroupLayout> {
+    bind_group_layout_cache.get_or_create(
+        device,
+        &wgpu::BindGroupLayoutDescriptor {
+            label: Some("Triangles Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        },
+    )
+}
+
+fn triangles_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    triangles_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Triangles Bind Group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: triangles_buffer.as_entire_binding(),
+        }],
+    })
+}
@@ -1,18 +1,73 @@
 use cgmath::ElementWise;
 
-use crate::ui::{Quad, Texture, TextureInfo, Ui};
+use crate::{
+    bind_group_layout_cache::BindGroupLayoutCache,
+    ui::{Quad, Texture, TextureAtlas, TextureInfo, TextureUploadQueue, Ui},
+};
 use std::{collections::HashMap, path::Path};
 
+#[cfg(feature = "bdf")]
+mod bdf;
+
+#[cfg(feature = "bdf")]
+pub use bdf::FontError;
+
+#[cfg(feature = "ttf")]
+mod ttf;
+
+#[cfg(feature = "ttf")]
+pub use ttf::InvalidFont;
+
 pub struct Font {
     line_height: usize,
     base: usize,
-    scale_width: usize,
-    scale_height: usize,
-    pages: HashMap<usize, Texture>,
+    pages: HashMap<usize, FontPage>,
     glyphs: HashMap<u32, Glyph>,
+    kernings: HashMap<(u32, u32), isize>,
+    // rasterizes glyphs that are not in `glyphs` on demand, only for ttf fonts
+    #[cfg(feature = "ttf")]
+    glyph_cache: Option<std::cell::RefCell<ttf::GlyphCache>>,
+}
+
+// a texture glyphs are drawn from, glyph coordinates are in texels of `width` by `height`
+struct FontPage {
+    texture: TextureInfo,
+    width: usize,
+    height: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAlign {
+    // `position` is the left edge of the first glyph
+    Left,
+    // `position` is the middle of the text
+    Center,
+    // `position` is the right edge of the last glyph
+    Right,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TextSpan<'a> {
+    pub text: &'a str,
+    pub scale: f32,
+    pub color: cgmath::Vector4<f32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TextStyle {
+    pub color: cgmath::Vector4<f32>,
+    // offset of a semi-transparent black copy of the text drawn behind it
+    pub shadow: Option<cgmath::Vector2<f32>>,
+    pub outline: Option<TextOutline>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TextOutline {
+    pub thickness: f32,
+    pub color: cgmath::Vector4<f32>,
+}
+
+#[derive(Debug, Clone, Copy)]
 pub struct Glyph {
     x: usize,
     y: usize,
@@ -32,25 +87,276 @@ impl Font {
         position: cgmath::Vector2<f32>,
         scale: f32,
         color: cgmath::Vector4<f32>,
-    ) {
-        let mut width = 0.0;
-        {
-            for c in s.chars() {
-                let Some(glyph) = self.glyphs.get(&(c as u32)) else {
-                    continue;
-                };
-                width += glyph.xadvance as f32 / self.line_height as f32 * scale;
+    ) -> cgmath::Vector4<f32> {
+        self.draw_str_aligned(ui, s, position, scale, color, TextAlign::Center)
+    }
+
+    pub fn draw_str_aligned(
+        &self,
+        ui: &mut Ui,
+        s: &str,
+        position: cgmath::Vector2<f32>,
+        scale: f32,
+        color: cgmath::Vector4<f32>,
+        align: TextAlign,
+    ) -> cgmath::Vector4<f32> {
+        let width = self.measure_str(s, scale).x;
+        let start_x = match align {
+            TextAlign::Left => position.x,
+            TextAlign::Center => position.x - width * 0.5,
+            TextAlign::Right => position.x - width,
+        };
+        let start = cgmath::vec2(start_x, position.y);
+        let end_x = self.draw_glyphs(ui, s, start, scale, color);
+        self.line_rect(start, end_x, scale)
+    }
+
+    pub fn draw_str_styled(
+        &self,
+        ui: &mut Ui,
+        s: &str,
+        position: cgmath::Vector2<f32>,
+        scale: f32,
+        style: TextStyle,
+        align: TextAlign,
+    ) -> cgmath::Vector4<f32> {
+        let width = self.measure_str(s, scale).x;
+        let start_x = match align {
+            TextAlign::Left => position.x,
+            TextAlign::Center => position.x - width * 0.5,
+            TextAlign::Right => position.x - width,
+        };
+        let start = cgmath::vec2(start_x, position.y);
+
+        // each effect is drawn for the whole string before the next,
+        // so the effects of one glyph never cover a neighbouring glyph
+        if let Some(offset) = style.shadow {
+            let shadow_color = cgmath::vec4(0.0, 0.0, 0.0, 0.5 * style.color.w);
+            self.draw_glyphs(ui, s, start + offset, scale, shadow_color);
+        }
+        if let Some(outline) = style.outline {
+            for x in -1..=1 {
+                for y in -1..=1 {
+                    if x == 0 && y == 0 {
+                        continue;
+                    }
+                    let offset = cgmath::vec2(x as f32, y as f32) * outline.thickness;
+                    self.draw_glyphs(ui, s, start + offset, scale, outline.color);
+                }
             }
         }
+        let end_x = self.draw_glyphs(ui, s, start, scale, style.color);
+        self.line_rect(start, end_x, scale)
+    }
 
-        let mut position = cgmath::vec2(position.x - width * 0.5, position.y);
+    // draws the spans one after another on the same baseline
+    pub fn draw_spans(
+        &self,
+        ui: &mut Ui,
+        spans: &[TextSpan<'_>],
+        position: cgmath::Vector2<f32>,
+        align: TextAlign,
+    ) -> cgmath::Vector4<f32> {
+        let width = spans
+            .iter()
+            .map(|span| self.measure_str(span.text, span.scale).x)
+            .sum::<f32>();
+        let start_x = match align {
+            TextAlign::Left => position.x,
+            TextAlign::Center => position.x - width * 0.5,
+            TextAlign::Right => position.x - width,
+        };
+
+        let mut rect = cgmath::vec4(start_x, position.y, start_x, position.y);
+        for span in spans {
+            let start = cgmath::vec2(rect.z, position.y);
+            let end_x = self.draw_glyphs(ui, span.text, start, span.scale, span.color);
+            let span_rect = self.line_rect(start, end_x, span.scale);
+            rect.y = rect.y.min(span_rect.y);
+            rect.z = end_x;
+            rect.w = rect.w.max(span_rect.w);
+        }
+        rect
+    }
+
+    // stacks the glyphs top to bottom, one line height apart, centered on `position`
+    pub fn draw_str_vertical(
+        &self,
+        ui: &mut Ui,
+        s: &str,
+        position: cgmath::Vector2<f32>,
+        scale: f32,
+        color: cgmath::Vector4<f32>,
+    ) -> cgmath::Vector4<f32> {
+        let glyphs = s.chars().filter_map(|c| self.glyph(c)).collect::<Vec<_>>();
+        let height = glyphs.len() as f32 * scale;
+        let top = position.y + height * 0.5;
+        let baseline_offset = self.base as f32 / self.line_height as f32 * scale;
+
+        let mut rect = cgmath::vec4(position.x, top - height, position.x, top);
+        for (i, glyph) in glyphs.iter().enumerate() {
+            let advance = glyph.xadvance as f32 / self.line_height as f32 * scale;
+            let glyph_position = cgmath::vec2(
+                position.x - advance * 0.5,
+                top - i as f32 * scale - baseline_offset,
+            );
+            self.draw_glyph(ui, glyph, glyph_position, scale, color);
+            rect.x = rect.x.min(glyph_position.x);
+            rect.z = rect.z.max(glyph_position.x + advance);
+        }
+        rect
+    }
+
+    // draws `s` with its left edge at `start`, returning the x of its right edge
+    fn draw_glyphs(
+        &self,
+        ui: &mut Ui,
+        s: &str,
+        start: cgmath::Vector2<f32>,
+        scale: f32,
+        color: cgmath::Vector4<f32>,
+    ) -> f32 {
+        let mut position = start;
+        let mut previous = None;
         for c in s.chars() {
-            let Some(glyph) = self.glyphs.get(&(c as u32)) else {
+            let Some(glyph) = self.glyph(c) else {
                 continue;
             };
-            self.draw_glyph(ui, glyph, position, scale, color);
+            position.x += self.kerning(previous, c, scale);
+            self.draw_glyph(ui, &glyph, position, scale, color);
             position.x += glyph.xadvance as f32 / self.line_height as f32 * scale;
+            previous = Some(c);
         }
+        position.x
+    }
+
+    // the (width, height) of the line of text that draw_str would draw
+    pub fn measure_str(&self, s: &str, scale: f32) -> cgmath::Vector2<f32> {
+        let width = str_width(
+            s,
+            scale,
+            self.line_height,
+            |c| self.glyph(c),
+            &self.kernings,
+        );
+        cgmath::vec2(width, scale)
+    }
+
+    // the byte index of the character in `s` that `x_offset` from the left edge of the text is on,
+    // rounding to the nearest character boundary, or `s.len()` if it is past the middle of the last glyph
+    pub fn char_at_x_offset(&self, s: &str, x_offset: f32, scale: f32) -> usize {
+        let mut x = 0.0;
+        let mut previous = None;
+        for (index, c) in s.char_indices() {
+            let Some(glyph) = self.glyph(c) else {
+                continue;
+            };
+            x += self.kerning(previous, c, scale);
+            let advance = glyph.xadvance as f32 / self.line_height as f32 * scale;
+            if x_offset < x + advance * 0.5 {
+                return index;
+            }
+            x += advance;
+            previous = Some(c);
+        }
+        s.len()
+    }
+
+    // the extra horizontal advance between `previous` and `c`
+    fn kerning(&self, previous: Option<char>, c: char, scale: f32) -> f32 {
+        let Some(previous) = previous else {
+            return 0.0;
+        };
+        self.kernings
+            .get(&(previous as u32, c as u32))
+            .map_or(0.0, |&amount| {
+                amount as f32 / self.line_height as f32 * scale
+            })
+    }
+
+    // draws each line of `text` below the previous one, returning the (width, height) of the block
+    pub fn draw_str_multiline(
+        &self,
+        ui: &mut Ui,
+        text: &str,
+        position: cgmath::Vector2<f32>,
+        scale: f32,
+        color: cgmath::Vector4<f32>,
+    ) -> cgmath::Vector2<f32> {
+        let mut size = cgmath::vec2(0.0, 0.0);
+        for line in text.lines() {
+            let rect = self.draw_str(ui, line, position - cgmath::vec2(0.0, size.y), scale, color);
+            size.x = size.x.max(rect.z - rect.x);
+            size.y += scale;
+        }
+        size
+    }
+
+    // like draw_str_multiline, but also breaks lines between words so they fit in `max_width`
+    pub fn draw_text_wrapped(
+        &self,
+        ui: &mut Ui,
+        text: &str,
+        position: cgmath::Vector2<f32>,
+        max_width: f32,
+        scale: f32,
+        color: cgmath::Vector4<f32>,
+    ) -> cgmath::Vector2<f32> {
+        let mut size = cgmath::vec2(0.0, 0.0);
+        let mut draw_line = |line: &str| {
+            let rect = self.draw_str(ui, line, position - cgmath::vec2(0.0, size.y), scale, color);
+            size.x = size.x.max(rect.z - rect.x);
+            size.y += scale;
+        };
+
+        let mut line = String::new();
+        for paragraph in text.lines() {
+            for word in paragraph.split(' ') {
+                let old_length = line.len();
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(word);
+
+                // words that are wider than max_width on their own are left on their own line
+                if old_length > 0 && self.measure_str(&line, scale).x > max_width {
+                    line.truncate(old_length);
+                    draw_line(&line);
+                    line.clear();
+                    line.push_str(word);
+                }
+            }
+            draw_line(&line);
+            line.clear();
+        }
+        size
+    }
+
+    pub fn draw_number(
+        &self,
+        ui: &mut Ui,
+        value: f64,
+        decimal_places: u8,
+        position: cgmath::Vector2<f32>,
+        scale: f32,
+        color: cgmath::Vector4<f32>,
+    ) -> cgmath::Vector4<f32> {
+        let mut buffer = [0; 32];
+        let s = format_number(&mut buffer, value, decimal_places);
+        self.draw_str(ui, s, position, scale, color)
+    }
+
+    pub fn draw_int(
+        &self,
+        ui: &mut Ui,
+        value: i64,
+        position: cgmath::Vector2<f32>,
+        scale: f32,
+        color: cgmath::Vector4<f32>,
+    ) -> cgmath::Vector4<f32> {
+        let mut buffer = [0; 32];
+        let s = format_int(&mut buffer, value);
+        self.draw_str(ui, s, position, scale, color)
     }
 
     pub fn draw_char(
@@ -60,12 +366,38 @@ impl Font {
         position: cgmath::Vector2<f32>,
         scale: f32,
         color: cgmath::Vector4<f32>,
-    ) -> bool {
-        let Some(glyph) = self.glyphs.get(&(c as u32)) else {
-            return false;
-        };
-        self.draw_glyph(ui, glyph, position, scale, color);
-        true
+    ) -> Option<cgmath::Vector4<f32>> {
+        let glyph = self.glyph(c)?;
+        self.draw_glyph(ui, &glyph, position, scale, color);
+        Some(self.line_rect(
+            position,
+            position.x + glyph.xadvance as f32 / self.line_height as f32 * scale,
+            scale,
+        ))
+    }
+
+    // (x_min, y_min, x_max, y_max) of a full-height line of text starting at `start`
+    fn line_rect(
+        &self,
+        start: cgmath::Vector2<f32>,
+        end_x: f32,
+        scale: f32,
+    ) -> cgmath::Vector4<f32> {
+        let top = start.y + self.base as f32 / self.line_height as f32 * scale;
+        cgmath::vec4(start.x, top - scale, end_x, top)
+    }
+
+    fn glyph(&self, c: char) -> Option<Glyph> {
+        if let Some(&glyph) = self.glyphs.get(&(c as u32)) {
+            return Some(glyph);
+        }
+
+        #[cfg(feature = "ttf")]
+        if let Some(glyph_cache) = &self.glyph_cache {
+            return Some(glyph_cache.borrow_mut().get_or_rasterize(c));
+        }
+
+        None
     }
 
     fn draw_glyph(
@@ -76,7 +408,13 @@ impl Font {
         scale: f32,
         color: cgmath::Vector4<f32>,
     ) {
-        let page = self.pages[&glyph.page].clone();
+        let page = &self.pages[&glyph.page];
+        let (uv_offset, uv_size) = glyph_uv_rect(
+            glyph,
+            cgmath::vec2(page.width as f32, page.height as f32),
+            page.texture.uv_offset,
+            page.texture.uv_size,
+        );
 
         let size = cgmath::vec2(glyph.width as f32, -(glyph.height as f32))
             / self.line_height as f32
@@ -92,22 +430,28 @@ impl Font {
                     + size * 0.5,
                 size,
                 color,
+                gradient: None,
+                pulse_phase: None,
             },
             Some(TextureInfo {
-                texture: page,
-                uv_offset: cgmath::vec2(glyph.x as f32, glyph.y as f32).div_element_wise(
-                    cgmath::vec2(self.scale_width as f32, self.scale_height as f32),
-                ),
-                uv_size: cgmath::vec2(glyph.width as f32, glyph.height as f32).div_element_wise(
-                    cgmath::vec2(self.scale_width as f32, self.scale_height as f32),
-                ),
+                uv_offset,
+                uv_size,
+                ..page.texture.clone()
             }),
         );
     }
 
+    // text is scaled by `scale / line_height`, so a larger line height draws the glyphs smaller,
+    // the glyph metrics are left alone as they are also the glyph's location in the atlas
+    pub fn with_line_height_override(mut self, line_height: f32) -> Self {
+        self.line_height = (line_height.round() as usize).max(1);
+        self
+    }
+
     pub fn load(
         device: &wgpu::Device,
-        queue: &wgpu::Queue,
+        bind_group_layout_cache: &BindGroupLayoutCache,
+        texture_uploads: &mut TextureUploadQueue,
         font: impl AsRef<Path>,
     ) -> std::io::Result<Self> {
         let font_path = font.as_ref();
@@ -132,27 +476,23 @@ impl Font {
         }
 
         assert_eq!(page_count, images.len());
-        Ok(Self::from_raw(device, queue, &font, &images))
+        Ok(Self::from_raw(
+            device,
+            bind_group_layout_cache,
+            texture_uploads,
+            &font,
+            &images,
+        ))
     }
 
     pub fn from_raw(
         device: &wgpu::Device,
-        queue: &wgpu::Queue,
+        bind_group_layout_cache: &BindGroupLayoutCache,
+        texture_uploads: &mut TextureUploadQueue,
         font: &str,
         font_images: &HashMap<usize, impl AsRef<[u8]>>,
     ) -> Self {
-        let (face, unicode, smooth) = font
-            .lines()
-            .find(|line| line.starts_with("info "))
-            .map(|line| {
-                (
-                    parse_str(line, "face=").unwrap(),
-                    parse_uint(line, "unicode=").unwrap(),
-                    parse_uint(line, "smooth=").unwrap(),
-                )
-            })
-            .unwrap();
-        assert_ne!(unicode, 0);
+        let (face, smooth) = parse_info(font);
 
         let mut pages = HashMap::with_capacity(font_images.len());
         for (&id, image) in font_images {
@@ -161,8 +501,10 @@ impl Font {
                     .unwrap()
                     .to_rgba32f();
 
-            let texture = Texture::new(
+            // mipmapped so text drawn smaller than the font doesn't alias
+            let texture = Texture::with_mipmaps(
                 device,
+                bind_group_layout_cache,
                 &format!("{face} Page {id}"),
                 image.width(),
                 image.height(),
@@ -173,77 +515,274 @@ impl Font {
                     wgpu::FilterMode::Linear
                 },
             );
-            let t = texture.texture_view().texture();
-            queue.write_texture(
-                t.as_image_copy(),
-                bytemuck::cast_slice(&image),
-                wgpu::TexelCopyBufferLayout {
-                    offset: 0,
-                    bytes_per_row: Some(4 * 4 * t.width()),
-                    rows_per_image: None,
-                },
-                t.size(),
-            );
+            texture_uploads.push(&texture, image.into_raw());
 
-            pages.insert(id, texture);
+            pages.insert(id, TextureInfo::whole(texture));
         }
-        queue.submit(std::iter::empty());
 
-        let (line_height, base, scale_width, scale_height, page_count) = font
-            .lines()
-            .find(|line| line.starts_with("common "))
-            .map(|line| {
-                (
-                    parse_uint(line, "lineHeight=").unwrap(),
-                    parse_uint(line, "base=").unwrap(),
-                    parse_uint(line, "scaleW=").unwrap(),
-                    parse_uint(line, "scaleH=").unwrap(),
-                    parse_uint(line, "pages=").unwrap(),
-                )
-            })
-            .unwrap();
-        assert_eq!(page_count, pages.len());
+        Self::from_pages(font, pages)
+    }
 
-        let glyphs_count = font
-            .lines()
-            .find(|line| line.starts_with("chars "))
-            .and_then(|line| parse_uint(line, "count="))
-            .unwrap_or(0);
-        let mut glyphs = HashMap::with_capacity(glyphs_count);
+    /// like `from_raw`, but the pages are packed into the atlas so text can share a bind group with everything else in it
+    pub fn from_raw_in_atlas(
+        queue: &wgpu::Queue,
+        atlas: &mut TextureAtlas,
+        font: &str,
+        font_images: &HashMap<usize, impl AsRef<[u8]>>,
+    ) -> Self {
+        let (face, _) = parse_info(font);
 
-        for line in font.lines() {
-            if !line.starts_with("char ") {
-                continue;
-            }
+        let mut pages = HashMap::with_capacity(font_images.len());
+        for (&id, image) in font_images {
+            let image =
+                image::load_from_memory_with_format(image.as_ref(), image::ImageFormat::Png)
+                    .unwrap()
+                    .to_rgba8();
+            let page = atlas.pack(
+                queue,
+                &format!("{face} Page {id}"),
+                image.as_raw(),
+                image.width(),
+                image.height(),
+            );
+            pages.insert(id, page);
+        }
 
-            let id = parse_uint(line, "id=").unwrap() as u32;
-            let glyph = Glyph {
-                x: parse_uint(line, "x=").unwrap(),
-                y: parse_uint(line, "y=").unwrap(),
-                width: parse_uint(line, "width=").unwrap(),
-                height: parse_uint(line, "height=").unwrap(),
-                xoffset: parse_int(line, "xoffset=").unwrap(),
-                yoffset: parse_int(line, "yoffset=").unwrap(),
-                xadvance: parse_int(line, "xadvance=").unwrap(),
-                page: parse_uint(line, "page=").unwrap(),
-            };
+        Self::from_pages(font, pages)
+    }
+
+    fn from_pages(font: &str, pages: HashMap<usize, TextureInfo>) -> Self {
+        let (line_height, base, scale_width, scale_height, page_count) = parse_common(font);
+        assert_eq!(page_count, pages.len());
+        // every page of a bmfont is the same size
+        let pages = pages
+            .into_iter()
+            .map(|(id, texture)| {
+                let page = FontPage {
+                    texture,
+                    width: scale_width,
+                    height: scale_height,
+                };
+                (id, page)
+            })
+            .collect::<HashMap<_, _>>();
+
+        let glyphs = parse_glyphs(font);
+        for glyph in glyphs.values() {
             assert!(
                 pages.contains_key(&glyph.page),
                 "page id={} should exist",
                 glyph.page
             );
-            glyphs.insert(id, glyph);
         }
+        let kernings = parse_kernings(font);
 
         Self {
             line_height,
             base,
-            scale_width,
-            scale_height,
             pages,
             glyphs,
+            kernings,
+            #[cfg(feature = "ttf")]
+            glyph_cache: None,
+        }
+    }
+}
+
+// the glyph's (uv offset, uv size), where `page_size` is in texels and the page covers
+// `region_offset` and `region_size` of its texture
+fn glyph_uv_rect(
+    glyph: &Glyph,
+    page_size: cgmath::Vector2<f32>,
+    region_offset: cgmath::Vector2<f32>,
+    region_size: cgmath::Vector2<f32>,
+) -> (cgmath::Vector2<f32>, cgmath::Vector2<f32>) {
+    let uv_offset = region_offset
+        + cgmath::vec2(glyph.x as f32, glyph.y as f32)
+            .div_element_wise(page_size)
+            .mul_element_wise(region_size);
+    let uv_size = cgmath::vec2(glyph.width as f32, glyph.height as f32)
+        .div_element_wise(page_size)
+        .mul_element_wise(region_size);
+    (uv_offset, uv_size)
+}
+
+// the width of `s` when drawn, only using the metrics of its glyphs so no textures are needed
+fn str_width(
+    s: &str,
+    scale: f32,
+    line_height: usize,
+    glyph: impl Fn(char) -> Option<Glyph>,
+    kernings: &HashMap<(u32, u32), isize>,
+) -> f32 {
+    let mut width = 0;
+    let mut previous = None;
+    for c in s.chars() {
+        let Some(glyph) = glyph(c) else {
+            continue;
+        };
+        if let Some(previous) = previous {
+            width += kernings
+                .get(&(previous as u32, c as u32))
+                .copied()
+                .unwrap_or(0);
+        }
+        width += glyph.xadvance;
+        previous = Some(c);
+    }
+    width as f32 / line_height as f32 * scale
+}
+
+// (line height, base, scale width, scale height, page count)
+fn parse_common(font: &str) -> (usize, usize, usize, usize, usize) {
+    font.lines()
+        .find(|line| line.starts_with("common "))
+        .map(|line| {
+            (
+                parse_uint(line, "lineHeight=").unwrap(),
+                parse_uint(line, "base=").unwrap(),
+                parse_uint(line, "scaleW=").unwrap(),
+                parse_uint(line, "scaleH=").unwrap(),
+                parse_uint(line, "pages=").unwrap(),
+            )
+        })
+        .unwrap()
+}
+
+fn parse_glyphs(font: &str) -> HashMap<u32, Glyph> {
+    let glyphs_count = font
+        .lines()
+        .find(|line| line.starts_with("chars "))
+        .and_then(|line| parse_uint(line, "count="))
+        .unwrap_or(0);
+    let mut glyphs = HashMap::with_capacity(glyphs_count);
+
+    for line in font.lines() {
+        if !line.starts_with("char ") {
+            continue;
         }
+
+        let id = parse_uint(line, "id=").unwrap() as u32;
+        let glyph = Glyph {
+            x: parse_uint(line, "x=").unwrap(),
+            y: parse_uint(line, "y=").unwrap(),
+            width: parse_uint(line, "width=").unwrap(),
+            height: parse_uint(line, "height=").unwrap(),
+            xoffset: parse_int(line, "xoffset=").unwrap(),
+            yoffset: parse_int(line, "yoffset=").unwrap(),
+            xadvance: parse_int(line, "xadvance=").unwrap(),
+            page: parse_uint(line, "page=").unwrap(),
+        };
+        glyphs.insert(id, glyph);
+    }
+    glyphs
+}
+
+fn parse_kernings(font: &str) -> HashMap<(u32, u32), isize> {
+    let mut kernings = HashMap::new();
+    for line in font.lines() {
+        if !line.starts_with("kerning ") {
+            continue;
+        }
+
+        let first = parse_uint(line, "first=").unwrap() as u32;
+        let second = parse_uint(line, "second=").unwrap() as u32;
+        let amount = parse_int(line, "amount=").unwrap();
+        kernings.insert((first, second), amount);
+    }
+    kernings
+}
+
+pub fn format_number(buffer: &mut [u8; 32], value: f64, decimal_places: u8) -> &str {
+    if value.is_nan() {
+        return "NaN";
+    }
+    if value.is_infinite() {
+        return if value < 0.0 { "-inf" } else { "inf" };
+    }
+
+    let mut decimal_places = decimal_places.min(9);
+    if value.abs() >= u64::MAX as f64 {
+        return format_scientific(buffer, value, decimal_places);
+    }
+
+    // drop decimal places until the scaled value fits in a u64
+    while decimal_places > 0 && value.abs() * 10f64.powi(decimal_places.into()) >= u64::MAX as f64 {
+        decimal_places -= 1;
+    }
+
+    let multiplier = 10u64.pow(decimal_places.into());
+    let scaled = (value.abs() * multiplier as f64).round() as u64;
+    let end = buffer.len();
+    let start = write_fixed(buffer, end, scaled, decimal_places, value < 0.0);
+    std::str::from_utf8(&buffer[start..]).expect("only ascii should have been written")
+}
+
+// values too large to scale into a u64 are written like 1.23e45
+fn format_scientific(buffer: &mut [u8; 32], value: f64, decimal_places: u8) -> &str {
+    let multiplier = 10u64.pow(decimal_places.into());
+    let mut exponent = value.abs().log10().floor() as i32;
+    let mut scaled = (value.abs() / 10f64.powi(exponent) * multiplier as f64).round() as u64;
+    // rounding can carry into another digit, like 9.996e20 with two decimal places
+    if scaled >= 10 * multiplier {
+        scaled /= 10;
+        exponent += 1;
+    }
+
+    let end = buffer.len();
+    // the value is at least u64::MAX, so the exponent is never negative
+    let mut start = write_digits(buffer, end, exponent as u64, 1);
+    start -= 1;
+    buffer[start] = b'e';
+    let start = write_fixed(buffer, start, scaled, decimal_places, value < 0.0);
+    std::str::from_utf8(&buffer[start..]).expect("only ascii should have been written")
+}
+
+// writes `scaled / 10^decimal_places` so that it ends at `end`, returning the index of the first character
+fn write_fixed(
+    buffer: &mut [u8],
+    end: usize,
+    scaled: u64,
+    decimal_places: u8,
+    negative: bool,
+) -> usize {
+    let multiplier = 10u64.pow(decimal_places.into());
+    let mut start = end;
+    if decimal_places > 0 {
+        start = write_digits(buffer, start, scaled % multiplier, decimal_places.into());
+        start -= 1;
+        buffer[start] = b'.';
+    }
+    start = write_digits(buffer, start, scaled / multiplier, 1);
+    // values that round to zero are written without a sign
+    if negative && scaled != 0 {
+        start -= 1;
+        buffer[start] = b'-';
     }
+    start
+}
+
+pub fn format_int(buffer: &mut [u8; 32], value: i64) -> &str {
+    let end = buffer.len();
+    let mut start = write_digits(buffer, end, value.unsigned_abs(), 1);
+    if value < 0 {
+        start -= 1;
+        buffer[start] = b'-';
+    }
+
+    std::str::from_utf8(&buffer[start..]).expect("only ascii should have been written")
+}
+
+// writes the digits of value so that they end at `end`, returning the index of the first digit
+fn write_digits(buffer: &mut [u8], mut end: usize, mut value: u64, min_digits: usize) -> usize {
+    let mut digits = 0;
+    while value != 0 || digits < min_digits {
+        end -= 1;
+        buffer[end] = b'0' + (value % 10) as u8;
+        value /= 10;
+        digits += 1;
+    }
+    end
 }
 
 fn parse_int(mut s: &str, pat: &str) -> Option<isize> {
@@ -275,6 +814,23 @@ fn parse_uint(mut s: &str, pat: &str) -> Option<usize> {
     s[..len].parse().ok()
 }
 
+// the face name and whether the font is smooth, only unicode fonts are supported
+fn parse_info(font: &str) -> (&str, usize) {
+    let (face, unicode, smooth) = font
+        .lines()
+        .find(|line| line.starts_with("info "))
+        .map(|line| {
+            (
+                parse_str(line, "face=").unwrap(),
+                parse_uint(line, "unicode=").unwrap(),
+                parse_uint(line, "smooth=").unwrap(),
+            )
+        })
+        .unwrap();
+    assert_ne!(unicode, 0);
+    (face, smooth)
+}
+
 fn parse_str<'a>(mut s: &'a str, pat: &str) -> Option<&'a str> {
     let position = s.find(pat)? + pat.len();
     s = &s[position..];
@@ -293,3 +849,96 @@ fn parse_str<'a>(mut s: &'a str, pat: &str) -> Option<&'a str> {
     }
     Some(&s[..len])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longer_text_measures_wider() {
+        let font = include_str!("../../fonts/space_mono.fnt");
+        let (line_height, ..) = parse_common(font);
+        let glyphs = parse_glyphs(font);
+        let kernings = parse_kernings(font);
+        let width = |s| {
+            str_width(
+                s,
+                1.0,
+                line_height,
+                |c| glyphs.get(&(c as u32)).copied(),
+                &kernings,
+            )
+        };
+
+        assert!(width("FPS: 00.00") > width("FPS: 0"));
+        assert_eq!(width(""), 0.0);
+    }
+
+    #[test]
+    fn cache_glyph_maps_to_its_own_slot() {
+        // a 4x2 grid of 16 texel slots, which is a different size to the pre-rasterized page
+        let glyph = Glyph {
+            x: 32,
+            y: 16,
+            width: 8,
+            height: 12,
+            xoffset: 0,
+            yoffset: 0,
+            xadvance: 8,
+            page: 1,
+        };
+        let (uv_offset, uv_size) = glyph_uv_rect(
+            &glyph,
+            cgmath::vec2(64.0, 32.0),
+            cgmath::vec2(0.0, 0.0),
+            cgmath::vec2(1.0, 1.0),
+        );
+        assert_eq!(uv_offset, cgmath::vec2(0.5, 0.5));
+        assert_eq!(uv_size, cgmath::vec2(0.125, 0.375));
+
+        // the same slot in a page packed into the bottom right quarter of an atlas
+        let (uv_offset, uv_size) = glyph_uv_rect(
+            &glyph,
+            cgmath::vec2(64.0, 32.0),
+            cgmath::vec2(0.5, 0.5),
+            cgmath::vec2(0.5, 0.5),
+        );
+        assert_eq!(uv_offset, cgmath::vec2(0.75, 0.75));
+        assert_eq!(uv_size, cgmath::vec2(0.0625, 0.1875));
+    }
+
+    #[test]
+    fn numbers_format_like_display() {
+        let mut buffer = [0; 32];
+        for (value, decimal_places, expected) in [
+            (0.0, 2, "0.00"),
+            (12.345, 0, "12"),
+            (-1.5, 1, "-1.5"),
+            (-0.004, 2, "0.00"),
+            (-0.0, 2, "0.00"),
+            (9.999, 2, "10.00"),
+            (-9.999, 2, "-10.00"),
+            (1e19, 2, "10000000000000000000"),
+            (1e20, 2, "1.00e20"),
+            (-9.996e20, 2, "-1.00e21"),
+            (f64::MAX, 2, "1.80e308"),
+            (f64::INFINITY, 2, "inf"),
+            (f64::NEG_INFINITY, 2, "-inf"),
+            (f64::NAN, 2, "NaN"),
+        ] {
+            assert_eq!(
+                format_number(&mut buffer, value, decimal_places),
+                expected,
+                "{value} with {decimal_places} decimal places"
+            );
+        }
+    }
+
+    #[test]
+    fn ints_format_like_display() {
+        let mut buffer = [0; 32];
+        for value in [0, 7, -7, 1234567890, i64::MAX, i64::MIN] {
+            assert_eq!(format_int(&mut buffer, value), value.to_string());
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use super::{Font, FontPage, Glyph};
+use crate::{
+    bind_group_layout_cache::BindGroupLayoutCache,
+    ui::{Texture, TextureInfo, TextureUploadQueue},
+};
+use std::{collections::HashMap, fmt};
+
+#[derive(Debug)]
+pub enum FontError {
+    MissingFontBoundingBox,
+    InvalidLine { line: usize },
+    UnterminatedGlyph { line: usize },
+    NoGlyphs,
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontError::MissingFontBoundingBox => write!(f, "the font has no FONTBOUNDINGBOX"),
+            FontError::InvalidLine { line } => write!(f, "line {line} could not be parsed"),
+            FontError::UnterminatedGlyph { line } => {
+                write!(f, "the glyph starting on line {line} has no ENDCHAR")
+            }
+            FontError::NoGlyphs => write!(f, "the font has no glyphs"),
+        }
+    }
+}
+
+impl std::error::Error for FontError {}
+
+struct BdfGlyph {
+    encoding: u32,
+    advance: isize,
+    width: usize,
+    height: usize,
+    xoffset: isize,
+    yoffset: isize,
+    // one row of bits per line, most significant bit first
+    rows: Vec<Vec<u8>>,
+}
+
+impl Font {
+    pub fn from_bdf(
+        device: &wgpu::Device,
+        bind_group_layout_cache: &BindGroupLayoutCache,
+        texture_uploads: &mut TextureUploadQueue,
+        bdf: &str,
+    ) -> Result<Self, FontError> {
+        let mut bounding_box = None;
+        let mut ascent = None;
+        let mut descent = None;
+        let mut bdf_glyphs = vec![];
+
+        let mut lines = bdf
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()));
+        while let Some((line_number, line)) = lines.next() {
+            let mut words = line.split_ascii_whitespace();
+            match words.next() {
+                Some("FONTBOUNDINGBOX") => {
+                    let [width, height, xoffset, yoffset] = parse_numbers(words, line_number)?;
+                    bounding_box = Some((width, height, xoffset, yoffset));
+                }
+                Some("FONT_ASCENT") => ascent = Some(parse_numbers::<1>(words, line_number)?[0]),
+                Some("FONT_DESCENT") => descent = Some(parse_numbers::<1>(words, line_number)?[0]),
+                Some("STARTCHAR") => {
+                    if let Some(glyph) = parse_glyph(&mut lines, line_number)? {
+                        bdf_glyphs.push(glyph);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let (_, bounding_height, _, bounding_yoffset) =
+            bounding_box.ok_or(FontError::MissingFontBoundingBox)?;
+        let ascent = ascent.unwrap_or(bounding_height + bounding_yoffset);
+        let descent = descent.unwrap_or(-bounding_yoffset);
+        if bdf_glyphs.is_empty() {
+            return Err(FontError::NoGlyphs);
+        }
+
+        // pack every glyph into a grid of equally sized cells, with a pixel of padding between them
+        let cell_width = bdf_glyphs
+            .iter()
+            .map(|glyph| glyph.width)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let cell_height = bdf_glyphs
+            .iter()
+            .map(|glyph| glyph.height)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let columns = bdf_glyphs.len().isqrt().max(1);
+        let rows = bdf_glyphs.len().div_ceil(columns);
+        let scale_width = columns * cell_width;
+        let scale_height = rows * cell_height;
+
+        let mut texels = vec![0.0; scale_width * scale_height * 4];
+        let mut glyphs = HashMap::with_capacity(bdf_glyphs.len());
+        for (i, glyph) in bdf_glyphs.into_iter().enumerate() {
+            let x = (i % columns) * cell_width;
+            let y = (i / columns) * cell_height;
+
+            for (row_index, row) in glyph.rows.iter().enumerate() {
+                for column in 0..glyph.width {
+                    let byte = row.get(column / 8).copied().unwrap_or(0);
+                    if byte & (0x80 >> (column % 8)) != 0 {
+                        let texel = ((y + row_index) * scale_width + x + column) * 4;
+                        texels[texel..][..4].copy_from_slice(&[1.0, 1.0, 1.0, 1.0]);
+                    }
+                }
+            }
+
+            glyphs.insert(
+                glyph.encoding,
+                Glyph {
+                    x,
+                    y,
+                    width: glyph.width,
+                    height: glyph.height,
+                    xoffset: glyph.xoffset,
+                    // bdf offsets are from the baseline to the bottom of the glyph,
+                    // bmfont offsets are from the top of the line to the top of the glyph
+                    yoffset: ascent - (glyph.yoffset + glyph.height as isize),
+                    xadvance: glyph.advance,
+                    page: 0,
+                },
+            );
+        }
+
+        let texture = Texture::new(
+            device,
+            bind_group_layout_cache,
+            "BDF Font Page 0",
+            scale_width as _,
+            scale_height as _,
+            wgpu::TextureFormat::Rgba8Unorm,
+            wgpu::TextureUsages::COPY_DST,
+            wgpu::FilterMode::Nearest,
+            wgpu::AddressMode::ClampToEdge,
+        );
+        texture_uploads.push(&texture, texels);
+
+        Ok(Self {
+            line_height: (ascent + descent).max(1) as _,
+            base: ascent.max(0) as _,
+            pages: HashMap::from([(
+                0,
+                FontPage {
+                    texture: TextureInfo::whole(texture),
+                    width: scale_width,
+                    height: scale_height,
+                },
+            )]),
+            glyphs,
+            kernings: HashMap::new(),
+            #[cfg(feature = "ttf")]
+            glyph_cache: None,
+        })
+    }
+}
+
+// parses everything after a STARTCHAR line, returning None for glyphs without a unicode encoding
+fn parse_glyph<'a>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    start_line: usize,
+) -> Result<Option<BdfGlyph>, FontError> {
+    let mut encoding = None;
+    let mut advance = 0;
+    let mut bounding_box = None;
+
+    while let Some((line_number, line)) = lines.next() {
+        let mut words = line.split_ascii_whitespace();
+        match words.next() {
+            Some("ENCODING") => encoding = Some(parse_numbers::<1>(words, line_number)?[0]),
+            Some("DWIDTH") => advance = parse_numbers::<2>(words, line_number)?[0],
+            Some("BBX") => bounding_box = Some(parse_numbers::<4>(words, line_number)?),
+            Some("BITMAP") => {
+                let [width, height, xoffset, yoffset] =
+                    bounding_box.ok_or(FontError::InvalidLine { line: line_number })?;
+                let width = usize::try_from(width)
+                    .map_err(|_| FontError::InvalidLine { line: line_number })?;
+                let height = usize::try_from(height)
+                    .map_err(|_| FontError::InvalidLine { line: line_number })?;
+
+                let mut rows = Vec::with_capacity(height);
+                for (line_number, line) in lines.by_ref() {
+                    if line == "ENDCHAR" {
+                        return Ok(encoding
+                            .and_then(|encoding| u32::try_from(encoding).ok())
+                            .map(|encoding| BdfGlyph {
+                                encoding,
+                                advance,
+                                width,
+                                height: rows.len(),
+                                xoffset,
+                                yoffset,
+                                rows,
+                            }));
+                    }
+
+                    if rows.len() == height || !line.is_ascii() || line.len() % 2 != 0 {
+                        return Err(FontError::InvalidLine { line: line_number });
+                    }
+                    let row = (0..line.len())
+                        .step_by(2)
+                        .map(|i| u8::from_str_radix(&line[i..i + 2], 16))
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|_| FontError::InvalidLine { line: line_number })?;
+                    rows.push(row);
+                }
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    Err(FontError::UnterminatedGlyph { line: start_line })
+}
+
+fn parse_numbers<'a, const N: usize>(
+    mut words: impl Iterator<Item = &'a str>,
+    line: usize,
+) -> Result<[isize; N], FontError> {
+    let mut numbers = [0; N];
+    for number in &mut numbers {
+        *number = words
+            .next()
+            .and_then(|word| word.parse().ok())
+            .ok_or(FontError::InvalidLine { line })?;
+    }
+    Ok(numbers)
+}
@@ -0,0 +1,357 @@
+use super::{Font, FontPage, Glyph};
+use crate::{
+    bind_group_layout_cache::BindGroupLayoutCache,
+    ui::{Texture, TextureInfo, TextureUploadQueue},
+};
+use ab_glyph::{Font as _, ScaleFont as _};
+use lru::LruCache;
+use std::{cell::RefCell, collections::HashMap, num::NonZeroUsize};
+
+pub use ab_glyph::InvalidFont;
+
+const GLYPH_CACHE_PAGE: usize = 1;
+const DEFAULT_GLYPH_CACHE_SIZE: usize = 256;
+
+// glyphs outside of the pre-rasterized range, rasterized into their own page the first time they are drawn
+pub(super) struct GlyphCache {
+    font: ab_glyph::FontVec,
+    point_size: f32,
+    texture: Texture,
+    cell_width: usize,
+    cell_height: usize,
+    columns: usize,
+    glyphs: LruCache<u32, Glyph>,
+    pending_uploads: Vec<GlyphUpload>,
+}
+
+struct GlyphUpload {
+    x: usize,
+    y: usize,
+    texels: Vec<f32>,
+}
+
+impl GlyphCache {
+    fn new(
+        device: &wgpu::Device,
+        bind_group_layout_cache: &BindGroupLayoutCache,
+        font: ab_glyph::FontVec,
+        point_size: f32,
+        capacity: usize,
+    ) -> Self {
+        let capacity = NonZeroUsize::new(capacity).expect("the glyph cache size should not be 0");
+
+        // every slot is a square the height of a line, which fits all but the most unusual glyphs
+        let scaled_font = font.as_scaled(point_size);
+        let cell_size = (scaled_font.ascent() - scaled_font.descent()).ceil() as usize + 1;
+        let columns = capacity.get().isqrt();
+        let rows = capacity.get().div_ceil(columns);
+
+        let texture = Texture::new(
+            device,
+            bind_group_layout_cache,
+            "TTF Font Glyph Cache",
+            (columns * cell_size) as _,
+            (rows * cell_size) as _,
+            wgpu::TextureFormat::Rgba32Float,
+            wgpu::TextureUsages::COPY_DST,
+            wgpu::FilterMode::Linear,
+            wgpu::AddressMode::ClampToEdge,
+        );
+
+        Self {
+            font,
+            point_size,
+            texture,
+            cell_width: cell_size,
+            cell_height: cell_size,
+            columns,
+            glyphs: LruCache::new(capacity),
+            pending_uploads: vec![],
+        }
+    }
+
+    // the cache texture is sized by its slots, not like the pre-rasterized page
+    fn page(&self) -> FontPage {
+        let size = self.texture.texture_view().texture().size();
+        FontPage {
+            texture: TextureInfo::whole(self.texture.clone()),
+            width: size.width as _,
+            height: size.height as _,
+        }
+    }
+
+    pub(super) fn get_or_rasterize(&mut self, c: char) -> Glyph {
+        if let Some(&glyph) = self.glyphs.get(&(c as u32)) {
+            return glyph;
+        }
+
+        // slots are handed out in order until the cache is full, then the least recently used slot is reused
+        let (x, y) = if self.glyphs.len() < self.glyphs.cap().get() {
+            let slot = self.glyphs.len();
+            (
+                (slot % self.columns) * self.cell_width,
+                (slot / self.columns) * self.cell_height,
+            )
+        } else {
+            let (_, evicted) = self
+                .glyphs
+                .pop_lru()
+                .expect("a full glyph cache should have a glyph to evict");
+            (evicted.x, evicted.y)
+        };
+
+        let scaled_font = self.font.as_scaled(self.point_size);
+        let ascent = scaled_font.ascent();
+        let glyph_id = scaled_font.glyph_id(c);
+        let outline = self.font.outline_glyph(
+            glyph_id.with_scale_and_position(self.point_size, ab_glyph::point(0.0, ascent)),
+        );
+
+        // the whole slot is written so nothing of the evicted glyph is left behind
+        let max_width = self.cell_width - 1;
+        let max_height = self.cell_height - 1;
+        let mut texels = vec![0.0; self.cell_width * self.cell_height * 4];
+        let (width, height, xoffset, yoffset) = match outline {
+            Some(outline) => {
+                outline.draw(|column, row, coverage| {
+                    let (column, row) = (column as usize, row as usize);
+                    if column < max_width && row < max_height {
+                        let texel = (row * self.cell_width + column) * 4;
+                        texels[texel..][..4].copy_from_slice(&[1.0, 1.0, 1.0, coverage]);
+                    }
+                });
+                let bounds = outline.px_bounds();
+                (
+                    (bounds.width() as usize).min(max_width),
+                    (bounds.height() as usize).min(max_height),
+                    bounds.min.x as isize,
+                    bounds.min.y as isize,
+                )
+            }
+            None => (0, 0, 0, 0),
+        };
+        self.pending_uploads.push(GlyphUpload { x, y, texels });
+
+        let glyph = Glyph {
+            x,
+            y,
+            width,
+            height,
+            xoffset,
+            yoffset,
+            xadvance: scaled_font.h_advance(glyph_id).round() as _,
+            page: GLYPH_CACHE_PAGE,
+        };
+        self.glyphs.push(c as u32, glyph);
+        glyph
+    }
+}
+
+impl Font {
+    // rasterizes the printable ascii range and the replacement character into a single page,
+    // other characters are rasterized into a glyph cache when they are first drawn
+    pub fn from_ttf(
+        device: &wgpu::Device,
+        bind_group_layout_cache: &BindGroupLayoutCache,
+        texture_uploads: &mut TextureUploadQueue,
+        ttf: &[u8],
+        point_size: f32,
+    ) -> Result<Self, InvalidFont> {
+        let font = ab_glyph::FontVec::try_from_vec(ttf.to_vec())?;
+        let scaled_font = font.as_scaled(point_size);
+        let ascent = scaled_font.ascent();
+
+        let chars = (0x20u8..=0x7E)
+            .map(char::from)
+            .chain([char::REPLACEMENT_CHARACTER])
+            .collect::<Vec<_>>();
+        // glyphs are positioned with their baseline at the ascent, so the bounds are relative to the top of the line
+        let outlines = chars
+            .iter()
+            .map(|&c| {
+                let glyph = scaled_font
+                    .glyph_id(c)
+                    .with_scale_and_position(point_size, ab_glyph::point(0.0, ascent));
+                font.outline_glyph(glyph)
+            })
+            .collect::<Vec<_>>();
+
+        // pack every glyph into a grid of equally sized cells, with a pixel of padding between them
+        let cell_width = outlines
+            .iter()
+            .flatten()
+            .map(|outline| outline.px_bounds().width() as usize)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let cell_height = outlines
+            .iter()
+            .flatten()
+            .map(|outline| outline.px_bounds().height() as usize)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let columns = chars.len().isqrt().max(1);
+        let rows = chars.len().div_ceil(columns);
+        let scale_width = columns * cell_width;
+        let scale_height = rows * cell_height;
+
+        let mut texels = vec![0.0; scale_width * scale_height * 4];
+        let mut glyphs = HashMap::with_capacity(chars.len());
+        for (i, (&c, outline)) in chars.iter().zip(&outlines).enumerate() {
+            let x = (i % columns) * cell_width;
+            let y = (i / columns) * cell_height;
+
+            let (width, height, xoffset, yoffset) = match outline {
+                Some(outline) => {
+                    outline.draw(|column, row, coverage| {
+                        let texel = ((y + row as usize) * scale_width + x + column as usize) * 4;
+                        texels[texel..][..4].copy_from_slice(&[1.0, 1.0, 1.0, coverage]);
+                    });
+                    let bounds = outline.px_bounds();
+                    (
+                        bounds.width() as usize,
+                        bounds.height() as usize,
+                        bounds.min.x as isize,
+                        bounds.min.y as isize,
+                    )
+                }
+                None => (0, 0, 0, 0),
+            };
+
+            glyphs.insert(
+                c as u32,
+                Glyph {
+                    x,
+                    y,
+                    width,
+                    height,
+                    xoffset,
+                    yoffset,
+                    xadvance: scaled_font.h_advance(scaled_font.glyph_id(c)).round() as _,
+                    page: 0,
+                },
+            );
+        }
+
+        let mut kernings = HashMap::new();
+        for &first in &chars {
+            for &second in &chars {
+                let amount = scaled_font
+                    .kern(scaled_font.glyph_id(first), scaled_font.glyph_id(second))
+                    .round() as isize;
+                if amount != 0 {
+                    kernings.insert((first as u32, second as u32), amount);
+                }
+            }
+        }
+
+        let texture = Texture::new(
+            device,
+            bind_group_layout_cache,
+            "TTF Font Page 0",
+            scale_width as _,
+            scale_height as _,
+            wgpu::TextureFormat::Rgba8Unorm,
+            wgpu::TextureUsages::COPY_DST,
+            wgpu::FilterMode::Linear,
+            wgpu::AddressMode::ClampToEdge,
+        );
+        texture_uploads.push(&texture, texels);
+
+        let line_height = (ascent - scaled_font.descent()).ceil().max(1.0) as _;
+        let base = ascent.round().max(0.0) as _;
+        let glyph_cache = GlyphCache::new(
+            device,
+            bind_group_layout_cache,
+            font,
+            point_size,
+            DEFAULT_GLYPH_CACHE_SIZE,
+        );
+
+        Ok(Self {
+            line_height,
+            base,
+            pages: HashMap::from([
+                (
+                    0,
+                    FontPage {
+                        texture: TextureInfo::whole(texture),
+                        width: scale_width,
+                        height: scale_height,
+                    },
+                ),
+                (GLYPH_CACHE_PAGE, glyph_cache.page()),
+            ]),
+            glyphs,
+            kernings,
+            glyph_cache: Some(RefCell::new(glyph_cache)),
+        })
+    }
+
+    // replaces the glyph cache of a ttf font with an empty one that holds `capacity` glyphs
+    pub fn with_glyph_cache_size(
+        mut self,
+        device: &wgpu::Device,
+        bind_group_layout_cache: &BindGroupLayoutCache,
+        capacity: usize,
+    ) -> Self {
+        let glyph_cache = self
+            .glyph_cache
+            .take()
+            .expect("only fonts loaded from ttf should have a glyph cache")
+            .into_inner();
+        let glyph_cache = GlyphCache::new(
+            device,
+            bind_group_layout_cache,
+            glyph_cache.font,
+            glyph_cache.point_size,
+            capacity,
+        );
+        self.pages.insert(GLYPH_CACHE_PAGE, glyph_cache.page());
+        self.glyph_cache = Some(RefCell::new(glyph_cache));
+        self
+    }
+
+    // writes the glyphs rasterized since the last call into the glyph cache texture,
+    // this should be called after drawing text and before the ui is rendered
+    pub fn flush_glyph_uploads(&self, queue: &wgpu::Queue) {
+        let Some(glyph_cache) = &self.glyph_cache else {
+            return;
+        };
+        let mut glyph_cache = glyph_cache.borrow_mut();
+        let GlyphCache {
+            texture,
+            cell_width,
+            cell_height,
+            pending_uploads,
+            ..
+        } = &mut *glyph_cache;
+
+        for GlyphUpload { x, y, texels } in pending_uploads.drain(..) {
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: texture.texture_view().texture(),
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: x as _,
+                        y: y as _,
+                        z: 0,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                bytemuck::cast_slice(&texels),
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some((4 * 4 * *cell_width) as _),
+                    rows_per_image: None,
+                },
+                wgpu::Extent3d {
+                    width: *cell_width as _,
+                    height: *cell_height as _,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{
+    Input,
+    ui::{Quad, QuadBorder, Ui},
+};
+use winit::event::MouseButton;
+
+/// a horizontal slider that is dragged with the left mouse button
+pub struct Slider {
+    pub position: cgmath::Vector2<f32>,
+    pub size: cgmath::Vector2<f32>,
+    pub min: f32,
+    pub max: f32,
+    pub value: f32,
+    pub color: cgmath::Vector4<f32>,
+    dragging: bool,
+}
+
+impl Slider {
+    pub fn new(
+        position: cgmath::Vector2<f32>,
+        size: cgmath::Vector2<f32>,
+        min: f32,
+        max: f32,
+        value: f32,
+    ) -> Self {
+        Self {
+            position,
+            size,
+            min,
+            max,
+            value,
+            color: cgmath::vec4(1.0, 1.0, 1.0, 1.0),
+            dragging: false,
+        }
+    }
+
+    /// starts dragging when the slider is clicked and moves the value to the mouse while dragging,
+    /// returns whether it is being dragged so the mouse can be ignored by everything else
+    pub fn update(&mut self, input: &Input) -> bool {
+        let mouse = input.mouse_position;
+        let min_corner = self.position - self.size * 0.5;
+        let max_corner = self.position + self.size * 0.5;
+        if input.mouse_button_just_pressed(MouseButton::Left)
+            && (min_corner.x..=max_corner.x).contains(&mouse.x)
+            && (min_corner.y..=max_corner.y).contains(&mouse.y)
+        {
+            self.dragging = true;
+        }
+        if !input.mouse_button_pressed(MouseButton::Left) {
+            self.dragging = false;
+        }
+
+        if self.dragging {
+            let t = ((mouse.x - min_corner.x) / self.size.x).clamp(0.0, 1.0);
+            self.value = self.min + t * (self.max - self.min);
+        }
+        self.dragging
+    }
+
+    pub fn dragging(&self) -> bool {
+        self.dragging
+    }
+
+    pub fn draw(&self, ui: &mut Ui) {
+        let t = ((self.value - self.min) / (self.max - self.min)).clamp(0.0, 1.0);
+        let left = self.position.x - self.size.x * 0.5;
+
+        let filled_width = self.size.x * t;
+        ui.push_quad(
+            Quad {
+                position: cgmath::vec2(left + filled_width * 0.5, self.position.y),
+                size: cgmath::vec2(filled_width, self.size.y),
+                color: self.color.truncate().extend(self.color.w * 0.4),
+                gradient: None,
+                pulse_phase: None,
+            },
+            None,
+        );
+        ui.push_quad_border(QuadBorder {
+            position: self.position,
+            size: self.size,
+            color: self.color,
+            thickness: 0.01,
+        });
+
+        let handle_size = cgmath::vec2(self.size.y * 0.5, self.size.y * 1.4);
+        ui.push_quad(
+            Quad {
+                position: cgmath::vec2(left + filled_width, self.position.y),
+                size: handle_size,
+                color: if self.dragging {
+                    self.color
+                } else {
+                    self.color.truncate().extend(self.color.w * 0.8)
+                },
+                gradient: None,
+                pulse_phase: None,
+            },
+            None,
+        );
+    }
+}
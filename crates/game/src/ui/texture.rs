@@ -1,17 +1,243 @@
+use crate::{
+    bind_group_layout_cache::BindGroupLayoutCache,
+    ui::TextureInfo,
+    validation::{ValidationFailure, ValidationScope},
+};
+use std::{collections::HashMap, sync::Arc};
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Texture {
     texture_view: wgpu::TextureView,
     bind_group: wgpu::BindGroup,
 }
 
+struct TextureUploadTask {
+    texture: Texture,
+    data: Vec<f32>,
+}
+
+#[derive(Default)]
+pub struct TextureUploadQueue {
+    pending: Vec<TextureUploadTask>,
+    // created by the first flush that needs it
+    mipmap_generator: Option<MipmapGenerator>,
+}
+
+impl TextureUploadQueue {
+    // queues a write of the whole texture, `data` is rgba texels in row order,
+    // the texture should be `Rgba32Float` or `Rgba8Unorm`
+    pub fn push(&mut self, texture: &Texture, data: Vec<f32>) {
+        let size = texture.texture_view().texture().size();
+        assert_eq!(data.len(), (size.width * size.height * 4) as usize);
+        self.pending.push(TextureUploadTask {
+            texture: texture.clone(),
+            data,
+        });
+    }
+
+    /// textures with mipmaps have them generated again from the new data
+    pub fn flush(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bind_group_layout_cache: &BindGroupLayoutCache,
+    ) {
+        for TextureUploadTask { texture, data } in self.pending.drain(..) {
+            let raw_texture = texture.texture_view().texture();
+            // the data is converted to the format of the texture
+            let bytes = match raw_texture.format() {
+                wgpu::TextureFormat::Rgba32Float => bytemuck::cast_slice(&data).to_vec(),
+                wgpu::TextureFormat::Rgba8Unorm => data
+                    .iter()
+                    .map(|&c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+                    .collect(),
+                format => panic!("uploading to {format:?} textures is not supported"),
+            };
+            let bytes_per_texel = raw_texture
+                .format()
+                .block_copy_size(None)
+                .expect("rgba formats should have a copy size");
+            queue.write_texture(
+                raw_texture.as_image_copy(),
+                &bytes,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_texel * raw_texture.width()),
+                    rows_per_image: None,
+                },
+                raw_texture.size(),
+            );
+            // textures with mipmaps have them generated again from the new data
+            if raw_texture.mip_level_count() > 1 {
+                self.mipmap_generator
+                    .get_or_insert_with(|| MipmapGenerator::new(device, bind_group_layout_cache))
+                    .generate(device, queue, &texture);
+            }
+        }
+        queue.submit(std::iter::empty());
+    }
+}
+
+/// the compute pipeline that fills in mip levels, it is created once and reused for every texture
+pub struct MipmapGenerator {
+    bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl MipmapGenerator {
+    pub fn new(device: &wgpu::Device, bind_group_layout_cache: &BindGroupLayoutCache) -> Self {
+        let bind_group_layout = bind_group_layout_cache.get_or_create(
+            device,
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Mipmap Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: wgpu::TextureFormat::Rgba8Unorm,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                ],
+            },
+        );
+        let shader = device.create_shader_module(wgpu::include_wgsl!(concat!(
+            env!("OUT_DIR"),
+            "/shaders/generate_mipmap.wgsl"
+        )));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Mipmap Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Mipmap Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("generate_mipmap"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        Self {
+            bind_group_layout,
+            pipeline,
+        }
+    }
+
+    /// fills every mip level after the first by averaging the level before it,
+    /// the texture should come from `Texture::with_mipmaps`
+    pub fn generate(&self, device: &wgpu::Device, queue: &wgpu::Queue, texture: &Texture) {
+        let texture = texture.texture_view().texture();
+        assert_eq!(
+            texture.format(),
+            wgpu::TextureFormat::Rgba8Unorm,
+            "mipmaps can only be generated for textures from `Texture::with_mipmaps`"
+        );
+        if texture.mip_level_count() == 1 {
+            return;
+        }
+
+        let _validation_scope = ValidationScope::new(
+            device,
+            "MipmapGenerator::generate",
+            ValidationFailure::Panic,
+        );
+
+        let level_view = |level| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                base_mip_level: level,
+                mip_level_count: Some(1),
+                ..Default::default()
+            })
+        };
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Mipmap Encoder"),
+        });
+        for level in 1..texture.mip_level_count() {
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Mipmap Bind Group"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&level_view(level - 1)),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&level_view(level)),
+                    },
+                ],
+            });
+
+            let size = texture
+                .size()
+                .mip_level_size(level, wgpu::TextureDimension::D2);
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Mipmap Compute Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(size.width.div_ceil(16), size.height.div_ceil(16), 1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+}
+
 impl Texture {
+    #[expect(clippy::too_many_arguments)]
     pub fn new(
         device: &wgpu::Device,
+        bind_group_layout_cache: &BindGroupLayoutCache,
+        name: &str,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        usage: wgpu::TextureUsages,
+        mag_filter: wgpu::FilterMode,
+        address_mode: wgpu::AddressMode,
+    ) -> Self {
+        let bind_group_layout = bind_group_layout(device, bind_group_layout_cache, format);
+        Self::with_layout(
+            device,
+            &bind_group_layout,
+            name,
+            width,
+            height,
+            format,
+            usage,
+            mag_filter,
+            address_mode,
+        )
+    }
+
+    /// for recreating textures without a `BindGroupLayoutCache`, `bind_group_layout` should come from `bind_group_layout`
+    #[expect(clippy::too_many_arguments)]
+    pub fn with_layout(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
         name: &str,
         width: u32,
         height: u32,
+        format: wgpu::TextureFormat,
         usage: wgpu::TextureUsages,
         mag_filter: wgpu::FilterMode,
+        address_mode: wgpu::AddressMode,
     ) -> Self {
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label: Some(name),
@@ -23,25 +249,80 @@ impl Texture {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba32Float,
+            format,
             usage: wgpu::TextureUsages::TEXTURE_BINDING | usage,
             view_formats: &[],
         });
+        Self::from_texture(
+            device,
+            bind_group_layout,
+            name,
+            texture,
+            mag_filter,
+            address_mode,
+        )
+    }
+
+    /// an `Rgba8Unorm` texture with every mip level down to 1x1, the levels are filled by `MipmapGenerator`
+    pub fn with_mipmaps(
+        device: &wgpu::Device,
+        bind_group_layout_cache: &BindGroupLayoutCache,
+        name: &str,
+        width: u32,
+        height: u32,
+        usage: wgpu::TextureUsages,
+        mag_filter: wgpu::FilterMode,
+    ) -> Self {
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(name),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: width.max(height).ilog2() + 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::STORAGE_BINDING
+                | usage,
+            view_formats: &[],
+        });
+        let bind_group_layout = bind_group_layout(device, bind_group_layout_cache, format);
+        Self::from_texture(
+            device,
+            &bind_group_layout,
+            name,
+            texture,
+            mag_filter,
+            wgpu::AddressMode::ClampToEdge,
+        )
+    }
 
+    fn from_texture(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        name: &str,
+        texture: wgpu::Texture,
+        mag_filter: wgpu::FilterMode,
+        address_mode: wgpu::AddressMode,
+    ) -> Self {
         let texture_view = texture.create_view(&Default::default());
         let texture_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            address_mode_w: address_mode,
             mag_filter,
             min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
             ..Default::default()
         });
 
-        let bind_group_layout = bind_group_layout(device);
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some(&format!("{name} Bind Group")),
-            layout: &bind_group_layout,
+            layout: bind_group_layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
@@ -60,6 +341,58 @@ impl Texture {
         }
     }
 
+    /// decodes a png and uploads it as `Rgba8Unorm`, the texture is the size of the image and linearly filtered
+    pub fn from_png_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bind_group_layout_cache: &BindGroupLayoutCache,
+        name: &str,
+        png_bytes: &[u8],
+    ) -> image::ImageResult<Self> {
+        Self::from_png_bytes_with_filter(
+            device,
+            queue,
+            bind_group_layout_cache,
+            name,
+            png_bytes,
+            wgpu::FilterMode::Linear,
+        )
+    }
+
+    pub fn from_png_bytes_with_filter(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bind_group_layout_cache: &BindGroupLayoutCache,
+        name: &str,
+        png_bytes: &[u8],
+        filter: wgpu::FilterMode,
+    ) -> image::ImageResult<Self> {
+        let image =
+            image::load_from_memory_with_format(png_bytes, image::ImageFormat::Png)?.into_rgba8();
+        let texture = Self::new(
+            device,
+            bind_group_layout_cache,
+            name,
+            image.width(),
+            image.height(),
+            wgpu::TextureFormat::Rgba8Unorm,
+            wgpu::TextureUsages::COPY_DST,
+            filter,
+            wgpu::AddressMode::ClampToEdge,
+        );
+        queue.write_texture(
+            texture.texture_view().texture().as_image_copy(),
+            image.as_raw(),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * image.width()),
+                rows_per_image: None,
+            },
+            texture.texture_view().texture().size(),
+        );
+        Ok(texture)
+    }
+
     pub fn texture_view(&self) -> &wgpu::TextureView {
         &self.texture_view
     }
@@ -69,26 +402,202 @@ impl Texture {
     }
 }
 
-pub(super) fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
-    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        label: Some("Texture Bind Group Layout"),
-        entries: &[
-            wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Texture {
-                    multisampled: false,
-                    view_dimension: wgpu::TextureViewDimension::D2,
-                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+// empty pixels left between packed images, so linear filtering doesn't blend neighbours together
+const ATLAS_PADDING: u32 = 1;
+
+/// one large texture that many images are packed into by name,
+/// so everything drawn from it can share a bind group
+pub struct TextureAtlas {
+    texture: Texture,
+    size: u32,
+    shelves: Vec<Shelf>,
+    regions: HashMap<String, AtlasRegion>,
+}
+
+/// a row of images as tall as the tallest image in it, filled from left to right
+struct Shelf {
+    y: u32,
+    height: u32,
+    used_width: u32,
+}
+
+#[derive(Clone, Copy)]
+struct AtlasRegion {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl TextureAtlas {
+    /// a square `Rgba8Unorm` atlas `size` pixels wide
+    pub fn new(
+        device: &wgpu::Device,
+        bind_group_layout_cache: &BindGroupLayoutCache,
+        name: &str,
+        size: u32,
+        mag_filter: wgpu::FilterMode,
+    ) -> Self {
+        Self {
+            texture: Texture::new(
+                device,
+                bind_group_layout_cache,
+                name,
+                size,
+                size,
+                wgpu::TextureFormat::Rgba8Unorm,
+                wgpu::TextureUsages::COPY_DST,
+                mag_filter,
+                wgpu::AddressMode::ClampToEdge,
+            ),
+            size,
+            shelves: vec![],
+            regions: HashMap::new(),
+        }
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// `rgba_bytes` are rgba texels in row order, packing a name again with the same size overwrites its image,
+    /// otherwise the image is given new space and the old space is not reused
+    pub fn pack(
+        &mut self,
+        queue: &wgpu::Queue,
+        name: &str,
+        rgba_bytes: &[u8],
+        width: u32,
+        height: u32,
+    ) -> TextureInfo {
+        assert_eq!(rgba_bytes.len(), (width * height * 4) as usize);
+
+        let region = match self.regions.get(name) {
+            Some(&region) if region.width == width && region.height == height => region,
+            _ => {
+                let (x, y) = self
+                    .allocate(width + ATLAS_PADDING, height + ATLAS_PADDING)
+                    .expect("the texture atlas should have room for the image");
+                let region = AtlasRegion {
+                    x,
+                    y,
+                    width,
+                    height,
+                };
+                self.regions.insert(name.to_owned(), region);
+                region
+            }
+        };
+
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: self.texture.texture_view().texture(),
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: region.x,
+                    y: region.y,
+                    z: 0,
                 },
-                count: None,
+                aspect: wgpu::TextureAspect::All,
+            },
+            rgba_bytes,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: None,
             },
-            wgpu::BindGroupLayoutEntry {
-                binding: 1,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                count: None,
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
             },
-        ],
-    })
+        );
+
+        self.texture_info(region)
+    }
+
+    pub fn get(&self, name: &str) -> Option<TextureInfo> {
+        self.regions
+            .get(name)
+            .map(|&region| self.texture_info(region))
+    }
+
+    // puts the rectangle on the shortest shelf it fits on, or starts a new shelf below the others
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if let Some(shelf) = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| shelf.height >= height && shelf.used_width + width <= self.size)
+            .min_by_key(|shelf| shelf.height)
+        {
+            let x = shelf.used_width;
+            shelf.used_width += width;
+            return Some((x, shelf.y));
+        }
+
+        let y = self
+            .shelves
+            .last()
+            .map_or(0, |shelf| shelf.y + shelf.height);
+        if width > self.size || y + height > self.size {
+            return None;
+        }
+        self.shelves.push(Shelf {
+            y,
+            height,
+            used_width: width,
+        });
+        Some((0, y))
+    }
+
+    fn texture_info(&self, region: AtlasRegion) -> TextureInfo {
+        let size = self.size as f32;
+        TextureInfo {
+            uv_offset: cgmath::vec2(region.x as f32, region.y as f32) / size,
+            uv_size: cgmath::vec2(region.width as f32, region.height as f32) / size,
+            ..TextureInfo::whole(self.texture.clone())
+        }
+    }
+}
+
+/// every float format shares the same layout, so textures of different formats can be drawn with the same pipelines
+pub(crate) fn bind_group_layout(
+    device: &wgpu::Device,
+    bind_group_layout_cache: &BindGroupLayoutCache,
+    format: wgpu::TextureFormat,
+) -> Arc<wgpu::BindGroupLayout> {
+    let (sample_type, sampler_type) = match format
+        .sample_type(None, None)
+        .expect("textures should be of a format that can be sampled")
+    {
+        wgpu::TextureSampleType::Float { .. } => (
+            wgpu::TextureSampleType::Float { filterable: true },
+            wgpu::SamplerBindingType::Filtering,
+        ),
+        sample_type => (sample_type, wgpu::SamplerBindingType::NonFiltering),
+    };
+    bind_group_layout_cache.get_or_create(
+        device,
+        &wgpu::BindGroupLayoutDescriptor {
+            label: Some("Texture Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(sampler_type),
+                    count: None,
+                },
+            ],
+        },
+    )
 }
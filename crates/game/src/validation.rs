@@ -0,0 +1,44 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationFailure {
+    Panic,
+    Log,
+}
+
+pub struct ValidationScope<'a> {
+    device: &'a wgpu::Device,
+    label: &'a str,
+    on_failure: ValidationFailure,
+}
+
+impl<'a> ValidationScope<'a> {
+    pub fn new(device: &'a wgpu::Device, label: &'a str, on_failure: ValidationFailure) -> Self {
+        if cfg!(debug_assertions) {
+            device.push_error_scope(wgpu::ErrorFilter::Validation);
+        }
+        Self {
+            device,
+            label,
+            on_failure,
+        }
+    }
+}
+
+impl Drop for ValidationScope<'_> {
+    fn drop(&mut self) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        let Some(error) = pollster::block_on(self.device.pop_error_scope()) else {
+            return;
+        };
+        match self.on_failure {
+            ValidationFailure::Panic if !std::thread::panicking() => {
+                panic!("validation error in {}: {error}", self.label)
+            }
+            ValidationFailure::Panic | ValidationFailure::Log => {
+                eprintln!("validation error in {}: {error}", self.label)
+            }
+        }
+    }
+}
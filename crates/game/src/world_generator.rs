@@ -0,0 +1,133 @@
+use crate::ray_tracing::pack_block;
+use math::noise::simplex4;
+use rand::{Rng, SeedableRng, rngs::SmallRng};
+
+pub struct WorldGenerator {
+    seed: u64,
+    rng: SmallRng,
+}
+
+impl WorldGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: SmallRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn reset(&mut self, seed: u64) {
+        *self = Self::new(seed);
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn generate_blocks(&mut self, count: usize) -> Vec<u32> {
+        let stone = pack_block(1, 255, 255, 255);
+        std::iter::repeat_with(|| {
+            if self.rng.random_range(0.0..=1.0) > 0.99 {
+                stone
+            } else {
+                0
+            }
+        })
+        .take(count)
+        .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ProceduralSettings {
+    pub seed: u32,
+    /// blocks are placed wherever the noise is above this, 0.2 fills about 5% of the chunk,
+    /// the noise stays within about ±0.98 so thresholds near 1 leave the chunk empty
+    pub threshold: f32,
+    pub octaves: usize,
+    pub frequency: f32,
+}
+
+impl Default for ProceduralSettings {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            threshold: 0.2,
+            octaves: 4,
+            frequency: 0.04,
+        }
+    }
+}
+
+/// samples 4d fractal simplex noise at every block of the chunk, returning the (index, block)
+/// pairs of the filled blocks sorted by index, the w slices are split between all available threads
+pub fn generate_procedural(
+    settings: ProceduralSettings,
+    chunk_size: usize,
+    block: u32,
+) -> Vec<[u32; 2]> {
+    // fractal brownian motion, each octave doubles the frequency and halves the amplitude
+    let noise = |x: f32, y: f32, z: f32, w: f32| {
+        let mut value = 0.0;
+        let mut total_amplitude = 0.0;
+        let mut frequency = settings.frequency;
+        let mut amplitude = 1.0;
+        for octave in 0..settings.octaves {
+            let seed = u64::from(settings.seed) + octave as u64;
+            value += amplitude
+                * simplex4(
+                    x * frequency,
+                    y * frequency,
+                    z * frequency,
+                    w * frequency,
+                    seed,
+                );
+            total_amplitude += amplitude;
+            frequency *= 2.0;
+            amplitude *= 0.5;
+        }
+        // normalize back into [-1, 1] so the threshold does not depend on the octave count
+        if total_amplitude > 0.0 {
+            value / total_amplitude
+        } else {
+            0.0
+        }
+    };
+
+    let thread_count = std::thread::available_parallelism().map_or(1, |count| count.get());
+    let slices_per_thread = chunk_size.div_ceil(thread_count);
+    std::thread::scope(|scope| {
+        let threads = (0..chunk_size)
+            .step_by(slices_per_thread)
+            .map(|start| {
+                let noise = &noise;
+                scope.spawn(move || {
+                    let mut filled = vec![];
+                    for w in start..(start + slices_per_thread).min(chunk_size) {
+                        for z in 0..chunk_size {
+                            for y in 0..chunk_size {
+                                for x in 0..chunk_size {
+                                    let value = noise(x as f32, y as f32, z as f32, w as f32);
+                                    if value > settings.threshold {
+                                        let index = x + chunk_size
+                                            * (y + chunk_size * (z + chunk_size * w));
+                                        filled.push([index as u32, block]);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    filled
+                })
+            })
+            .collect::<Vec<_>>();
+
+        threads
+            .into_iter()
+            .flat_map(|thread| {
+                thread
+                    .join()
+                    .expect("the procedural generation thread should not panic")
+            })
+            .collect()
+    })
+}
@@ -0,0 +1,143 @@
+use cgmath::Array;
+
+/// a 4d axis aligned box, both corners are inclusive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb4 {
+    pub min: cgmath::Vector4<f32>,
+    pub max: cgmath::Vector4<f32>,
+}
+
+impl Aabb4 {
+    #[inline]
+    pub fn new(min: cgmath::Vector4<f32>, max: cgmath::Vector4<f32>) -> Self {
+        Self { min, max }
+    }
+
+    /// the smallest box containing every point, with no points the box is empty and contains nothing
+    pub fn from_points(points: impl Iterator<Item = cgmath::Vector4<f32>>) -> Self {
+        points.fold(
+            Self {
+                min: cgmath::Vector4::from_value(f32::INFINITY),
+                max: cgmath::Vector4::from_value(f32::NEG_INFINITY),
+            },
+            |aabb, point| Self {
+                min: cgmath::vec4(
+                    aabb.min.x.min(point.x),
+                    aabb.min.y.min(point.y),
+                    aabb.min.z.min(point.z),
+                    aabb.min.w.min(point.w),
+                ),
+                max: cgmath::vec4(
+                    aabb.max.x.max(point.x),
+                    aabb.max.y.max(point.y),
+                    aabb.max.z.max(point.z),
+                    aabb.max.w.max(point.w),
+                ),
+            },
+        )
+    }
+
+    pub fn contains(&self, point: cgmath::Vector4<f32>) -> bool {
+        (0..4).all(|i| self.min[i] <= point[i] && point[i] <= self.max[i])
+    }
+
+    pub fn intersects(&self, other: &Aabb4) -> bool {
+        (0..4).all(|i| self.min[i] <= other.max[i] && other.min[i] <= self.max[i])
+    }
+
+    /// grows the box by the margin along every axis in both directions
+    pub fn expand(&self, margin: f32) -> Aabb4 {
+        let margin = cgmath::Vector4::from_value(margin);
+        Self {
+            min: self.min - margin,
+            max: self.max + margin,
+        }
+    }
+
+    #[inline]
+    pub fn center(&self) -> cgmath::Vector4<f32> {
+        (self.min + self.max) * 0.5
+    }
+
+    #[inline]
+    pub fn size(&self) -> cgmath::Vector4<f32> {
+        self.max - self.min
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Aabb4 {
+        Aabb4::new(
+            cgmath::Vector4::from_value(0.0),
+            cgmath::Vector4::from_value(1.0),
+        )
+    }
+
+    #[test]
+    fn contains_is_inclusive() {
+        let aabb = unit_box();
+        assert!(aabb.contains(cgmath::vec4(0.5, 0.5, 0.5, 0.5)));
+        assert!(aabb.contains(cgmath::vec4(0.0, 1.0, 0.0, 1.0)));
+        assert!(!aabb.contains(cgmath::vec4(0.5, 0.5, 0.5, 1.1)));
+        assert!(!aabb.contains(cgmath::vec4(-0.1, 0.5, 0.5, 0.5)));
+    }
+
+    #[test]
+    fn touching_boxes_intersect() {
+        let aabb = unit_box();
+        let offset = |x| {
+            Aabb4::new(
+                cgmath::vec4(x, 0.0, 0.0, 0.0),
+                cgmath::vec4(x + 1.0, 1.0, 1.0, 1.0),
+            )
+        };
+        assert!(aabb.intersects(&aabb));
+        assert!(aabb.intersects(&offset(0.5)));
+        assert!(aabb.intersects(&offset(1.0)));
+        assert!(offset(1.0).intersects(&aabb));
+        assert!(!aabb.intersects(&offset(1.5)));
+    }
+
+    #[test]
+    fn expand_keeps_the_center() {
+        let aabb = unit_box().expand(0.5);
+        assert_eq!(aabb.min, cgmath::Vector4::from_value(-0.5));
+        assert_eq!(aabb.max, cgmath::Vector4::from_value(1.5));
+        assert_eq!(aabb.center(), unit_box().center());
+        assert_eq!(aabb.size(), cgmath::Vector4::from_value(2.0));
+    }
+
+    #[test]
+    fn center_and_size() {
+        let aabb = Aabb4::new(
+            cgmath::vec4(-1.0, 0.0, 2.0, -4.0),
+            cgmath::vec4(1.0, 4.0, 3.0, 0.0),
+        );
+        assert_eq!(aabb.center(), cgmath::vec4(0.0, 2.0, 2.5, -2.0));
+        assert_eq!(aabb.size(), cgmath::vec4(2.0, 4.0, 1.0, 4.0));
+    }
+
+    #[test]
+    fn from_points_bounds_every_point() {
+        let points = [
+            cgmath::vec4(1.0, -2.0, 0.0, 3.0),
+            cgmath::vec4(-1.0, 2.0, 0.5, 0.0),
+            cgmath::vec4(0.0, 0.0, -0.5, 1.0),
+        ];
+        let aabb = Aabb4::from_points(points.into_iter());
+        assert_eq!(aabb.min, cgmath::vec4(-1.0, -2.0, -0.5, 0.0));
+        assert_eq!(aabb.max, cgmath::vec4(1.0, 2.0, 0.5, 3.0));
+        assert!(points.into_iter().all(|point| aabb.contains(point)));
+    }
+
+    #[test]
+    fn from_no_points_is_empty() {
+        let aabb = Aabb4::from_points(std::iter::empty());
+        assert!(!aabb.contains(cgmath::vec4(0.0, 0.0, 0.0, 0.0)));
+        assert!(!aabb.intersects(&unit_box()));
+        assert!(!unit_box().intersects(&aabb));
+    }
+}
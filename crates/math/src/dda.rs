@@ -0,0 +1,121 @@
+/// the integer coordinates of every voxel the ray passes through in order, starting with the one the origin is in,
+/// this is the 4d version of "A Fast Voxel Traversal Algorithm for Ray Tracing" by Amanatides and Woo,
+/// at most `max_steps` voxels are stepped into after the first, and nothing is stepped into if the direction is zero
+pub fn dda_4d(
+    origin: cgmath::Vector4<f32>,
+    direction: cgmath::Vector4<f32>,
+    max_steps: u32,
+) -> impl Iterator<Item = (i32, i32, i32, i32)> {
+    let origin: [f32; 4] = origin.into();
+    let direction: [f32; 4] = direction.into();
+
+    let mut voxel = origin.map(|c| c.floor() as i32);
+    let step = direction.map(|d| if d < 0.0 { -1 } else { 1 });
+    // how far along the ray it takes to cross a whole voxel along each axis
+    let distance_per_voxel = direction.map(|d| 1.0 / d.abs());
+    // how far along the ray the next voxel boundary along each axis is
+    let mut next_boundary: [f32; 4] = std::array::from_fn(|i| {
+        if direction[i] == 0.0 {
+            f32::INFINITY
+        } else if direction[i] < 0.0 {
+            (origin[i] - voxel[i] as f32) * distance_per_voxel[i]
+        } else {
+            (voxel[i] as f32 + 1.0 - origin[i]) * distance_per_voxel[i]
+        }
+    });
+
+    std::iter::once(voxel)
+        .chain((0..max_steps).map_while(move |_| {
+            let axis = (0..4)
+                .min_by(|&a, &b| next_boundary[a].total_cmp(&next_boundary[b]))
+                .expect("there should be 4 axes");
+            if next_boundary[axis].is_infinite() {
+                return None;
+            }
+
+            voxel[axis] += step[axis];
+            next_boundary[axis] += distance_per_voxel[axis];
+            Some(voxel)
+        }))
+        .map(|[x, y, z, w]| (x, y, z, w))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voxels(
+        origin: cgmath::Vector4<f32>,
+        direction: cgmath::Vector4<f32>,
+        max_steps: u32,
+    ) -> Vec<(i32, i32, i32, i32)> {
+        dda_4d(origin, direction, max_steps).collect()
+    }
+
+    #[test]
+    fn single_axis() {
+        assert_eq!(
+            voxels(
+                cgmath::vec4(0.5, 0.5, 0.5, 0.5),
+                cgmath::vec4(0.0, 0.0, 0.0, 1.0),
+                3,
+            ),
+            [(0, 0, 0, 0), (0, 0, 0, 1), (0, 0, 0, 2), (0, 0, 0, 3)],
+        );
+    }
+
+    #[test]
+    fn diagonal_crosses_each_boundary_in_order() {
+        // the x boundaries are crossed at 0.5, 1.5, .. and the y boundaries at 0.75, 1.75, ..
+        assert_eq!(
+            voxels(
+                cgmath::vec4(0.5, 0.25, 0.5, 0.5),
+                cgmath::vec4(1.0, 1.0, 0.0, 0.0),
+                4,
+            ),
+            [
+                (0, 0, 0, 0),
+                (1, 0, 0, 0),
+                (1, 1, 0, 0),
+                (2, 1, 0, 0),
+                (2, 2, 0, 0),
+            ],
+        );
+    }
+
+    #[test]
+    fn negative_direction_from_a_boundary() {
+        // starting exactly on a boundary the ray is already leaving the voxel it starts in
+        assert_eq!(
+            voxels(
+                cgmath::vec4(2.0, 0.5, 0.5, 0.5),
+                cgmath::vec4(-1.0, 0.0, 0.0, 0.0),
+                2,
+            ),
+            [(2, 0, 0, 0), (1, 0, 0, 0), (0, 0, 0, 0)],
+        );
+    }
+
+    #[test]
+    fn zero_direction_only_yields_the_start() {
+        assert_eq!(
+            voxels(
+                cgmath::vec4(-0.5, 1.5, 2.5, 3.5),
+                cgmath::vec4(0.0, 0.0, 0.0, 0.0),
+                10,
+            ),
+            [(-1, 1, 2, 3)],
+        );
+    }
+
+    #[test]
+    fn steps_are_capped() {
+        let direction = cgmath::vec4(0.3, -0.7, 0.2, 0.5);
+        for max_steps in [0, 1, 17] {
+            assert_eq!(
+                voxels(cgmath::vec4(0.1, 0.2, 0.3, 0.4), direction, max_steps).len(),
+                max_steps as usize + 1,
+            );
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use crate::Rotor;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Plane4 {
+    XY,
+    XZ,
+    XW,
+    YZ,
+    YW,
+    ZW,
+}
+
+impl Plane4 {
+    pub const ALL: [Plane4; 6] = [
+        Plane4::XY,
+        Plane4::XZ,
+        Plane4::XW,
+        Plane4::YZ,
+        Plane4::YW,
+        Plane4::ZW,
+    ];
+}
+
+/// a rotation as an angle in each of the 6 planes of 4d space, applied in the order of the fields
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Euler4 {
+    pub xy: f32,
+    pub xz: f32,
+    pub xw: f32,
+    pub yz: f32,
+    pub yw: f32,
+    pub zw: f32,
+}
+
+impl From<Euler4> for Rotor {
+    fn from(euler: Euler4) -> Self {
+        Rotor::rotate_xy(euler.xy)
+            .then(Rotor::rotate_xz(euler.xz))
+            .then(Rotor::rotate_xw(euler.xw))
+            .then(Rotor::rotate_yz(euler.yz))
+            .then(Rotor::rotate_yw(euler.yw))
+            .then(Rotor::rotate_zw(euler.zw))
+    }
+}
+
+impl Rotor {
+    pub fn from_plane_angle(plane: Plane4, angle: f32) -> Self {
+        match plane {
+            Plane4::XY => Rotor::rotate_xy(angle),
+            Plane4::XZ => Rotor::rotate_xz(angle),
+            Plane4::XW => Rotor::rotate_xw(angle),
+            Plane4::YZ => Rotor::rotate_yz(angle),
+            Plane4::YW => Rotor::rotate_yw(angle),
+            Plane4::ZW => Rotor::rotate_zw(angle),
+        }
+    }
+
+    /// the approximate angle in each plane, see `euler_angles`
+    pub fn to_planes(self) -> [(Plane4, f32); 6] {
+        let Euler4 {
+            xy,
+            xz,
+            xw,
+            yz,
+            yw,
+            zw,
+        } = self.euler_angles();
+        [
+            (Plane4::XY, xy),
+            (Plane4::XZ, xz),
+            (Plane4::XW, xw),
+            (Plane4::YZ, yz),
+            (Plane4::YW, yw),
+            (Plane4::ZW, zw),
+        ]
+    }
+}
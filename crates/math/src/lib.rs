@@ -1,6 +1,16 @@
+pub mod aabb;
+pub mod dda;
+pub mod euler;
+pub mod noise;
+pub mod ray;
+
+pub use aabb::Aabb4;
+pub use euler::{Euler4, Plane4};
 pub use impls::{NoE2Rotor, Rotor, Transform};
+pub use ray::Ray4;
 
 mod impls {
+    use crate::Euler4;
     use bytemuck::{Pod, Zeroable};
     use serde::{Deserialize, Serialize};
 
@@ -17,7 +27,7 @@ mod impls {
         group VgaNoE2Quadvector  = VgaNoE2Vector ^ VgaNoE2Trivector;
         group VgaNoE2Pentavector = VgaNoE2Vector ^ VgaNoE2Quadvector;
 
-        group NoE2Rotor = Scalar + VgaNoE2Bivector + VgaNoE2Quadvector;
+        group #[derive(Serialize, Deserialize)] NoE2Rotor = Scalar + VgaNoE2Bivector + VgaNoE2Quadvector;
 
         fn rotor_no_e2_then(a: NoE2Rotor, b: NoE2Rotor) -> NoE2Rotor {
             return b * a;
@@ -393,6 +403,45 @@ mod impls {
             }
         }
 
+        /// a random rotation made from a random angle in each of the xz, zw, and xw planes,
+        /// this is not uniformly distributed over all rotations, only some orientations are more likely than others
+        pub fn random(rng: &mut impl rand::Rng) -> Self {
+            let mut angle = || rng.random_range(0.0..std::f32::consts::TAU);
+            Self::rotate_xz(angle())
+                .then(Self::rotate_zw(angle()))
+                .then(Self::rotate_xw(angle()))
+        }
+
+        pub fn slerp(self, other: Self, t: f32) -> Self {
+            let a = [self.s, self.e1e3, self.e1e4, self.e3e4];
+            let mut b = [other.s, other.e1e3, other.e1e4, other.e3e4];
+
+            // a rotor and its negation are the same rotation, so go the short way around
+            let mut dot = (0..4).map(|i| a[i] * b[i]).sum::<f32>();
+            if dot < 0.0 {
+                b = b.map(|b| -b);
+                dot = -dot;
+            }
+
+            let (a_weight, b_weight) = if dot > 0.9995 {
+                // nearly parallel, so fall back to a normalised lerp to avoid dividing by ~0
+                (1.0 - t, t)
+            } else {
+                let angle = dot.acos();
+                let sin = angle.sin();
+                (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+            };
+            let [s, e1e3, e1e4, e3e4] = std::array::from_fn(|i| a[i] * a_weight + b[i] * b_weight);
+
+            let magnitude = (s * s + e1e3 * e1e3 + e1e4 * e1e4 + e3e4 * e3e4).sqrt();
+            Self {
+                s: s / magnitude,
+                e1e3: e1e3 / magnitude,
+                e1e4: e1e4 / magnitude,
+                e3e4: e3e4 / magnitude,
+            }
+        }
+
         #[inline]
         pub fn then(self, then: Self) -> Self {
             rotor_no_e2_then(self, then)
@@ -514,6 +563,75 @@ mod impls {
             }
         }
 
+        /// interpolates the components like `NoE2Rotor::slerp`, which is only exact when both rotors
+        /// stay in the same 3d subspace, otherwise the result is a close but not geodesic rotation
+        pub fn slerp(self, other: Self, t: f32) -> Self {
+            let a = [
+                self.s,
+                self.e1e2,
+                self.e1e3,
+                self.e1e4,
+                self.e2e3,
+                self.e2e4,
+                self.e3e4,
+                self.e1e2e3e4,
+            ];
+            let mut b = [
+                other.s,
+                other.e1e2,
+                other.e1e3,
+                other.e1e4,
+                other.e2e3,
+                other.e2e4,
+                other.e3e4,
+                other.e1e2e3e4,
+            ];
+
+            // a rotor and its negation are the same rotation, so go the short way around
+            let mut dot = (0..8).map(|i| a[i] * b[i]).sum::<f32>();
+            if dot < 0.0 {
+                b = b.map(|b| -b);
+                dot = -dot;
+            }
+
+            let (a_weight, b_weight) = if dot > 0.9995 {
+                // nearly parallel, so fall back to a normalised lerp to avoid dividing by ~0
+                (1.0 - t, t)
+            } else {
+                let angle = dot.min(1.0).acos();
+                let sin = angle.sin();
+                (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+            };
+            let c: [f32; 8] = std::array::from_fn(|i| a[i] * a_weight + b[i] * b_weight);
+
+            let magnitude = c.iter().map(|c| c * c).sum::<f32>().sqrt();
+            let [s, e1e2, e1e3, e1e4, e2e3, e2e4, e3e4, e1e2e3e4] = c.map(|c| c / magnitude);
+            Self {
+                s,
+                e1e2,
+                e1e3,
+                e1e4,
+                e2e3,
+                e2e4,
+                e3e4,
+                e1e2e3e4,
+            }
+        }
+
+        /// the angle of each plane's bivector component on its own, this only gives back the angles
+        /// the rotor was made from when they are in a single plane or in planes that don't share an axis
+        pub fn euler_angles(self) -> Euler4 {
+            let angle = |bivector: f32| 2.0 * bivector.atan2(self.s);
+            Euler4 {
+                xy: angle(self.e1e2),
+                xz: angle(self.e1e3),
+                xw: angle(self.e1e4),
+                yz: angle(self.e2e3),
+                yw: angle(self.e2e4),
+                zw: angle(self.e3e4),
+            }
+        }
+
         #[inline]
         pub fn then(self, then: Self) -> Self {
             rotor_then(self, then)
@@ -647,6 +765,13 @@ mod impls {
             transform_reverse(self)
         }
 
+        /// undoes the transform, for a normalised transform the reverse rotates the other way
+        /// and moves back by the rotated offset, so this is the same as `reverse`
+        #[inline]
+        pub fn inverse(self) -> Self {
+            self.reverse()
+        }
+
         #[inline]
         pub fn transform_point(self, point: cgmath::Vector4<f32>) -> cgmath::Vector4<f32> {
             let (Scalar { s: x }, Scalar { s: y }, Scalar { s: z }, Scalar { s: w }) =
@@ -755,5 +880,185 @@ mod impls {
                 e1e2e3e4,
             }
         }
+        /// reads a 3d homogeneous matrix, the rotation is taken from the upper 3x3 block,
+        /// which should be orthonormal, and the translation from the last column,
+        /// so the transform never moves or rotates anything along w
+        pub fn from_matrix4(m: cgmath::Matrix4<f32>) -> Self {
+            // `m[column][row]`, the rotor is found like a quaternion, dividing by the largest
+            // of the 4 possible terms so it still works for half turns
+            let trace = m[0][0] + m[1][1] + m[2][2];
+            let (s, e1e2, e1e3, e2e3) = if trace > 0.0 {
+                let scale = (trace + 1.0).sqrt() * 2.0;
+                (
+                    scale * 0.25,
+                    (m[0][1] - m[1][0]) / scale,
+                    (m[0][2] - m[2][0]) / scale,
+                    (m[1][2] - m[2][1]) / scale,
+                )
+            } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+                let scale = (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt() * 2.0;
+                (
+                    (m[1][2] - m[2][1]) / scale,
+                    (m[0][2] + m[2][0]) / scale,
+                    -(m[0][1] + m[1][0]) / scale,
+                    scale * 0.25,
+                )
+            } else if m[1][1] > m[2][2] {
+                let scale = (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt() * 2.0;
+                (
+                    (m[2][0] - m[0][2]) / scale,
+                    (m[1][2] + m[2][1]) / scale,
+                    -scale * 0.25,
+                    (m[1][0] + m[0][1]) / scale,
+                )
+            } else {
+                let scale = (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt() * 2.0;
+                (
+                    (m[0][1] - m[1][0]) / scale,
+                    scale * 0.25,
+                    -(m[1][2] + m[2][1]) / scale,
+                    (m[2][0] + m[0][2]) / scale,
+                )
+            };
+            let magnitude = (s * s + e1e2 * e1e2 + e1e3 * e1e3 + e2e3 * e2e3).sqrt();
+            let rotor = Rotor {
+                s: s / magnitude,
+                e1e2: e1e2 / magnitude,
+                e1e3: e1e3 / magnitude,
+                e2e3: e2e3 / magnitude,
+                ..Rotor::zero()
+            };
+
+            Self::translation(m[3].truncate().extend(0.0)).then(Self::from_rotor(rotor))
+        }
+
+        /// the inverse of `from_matrix4`, anything along w is dropped,
+        /// so this is only exact for transforms that keep w where it is
+        pub fn to_matrix4(self) -> cgmath::Matrix4<f32> {
+            cgmath::Matrix4::from_cols(
+                self.x().truncate().extend(0.0),
+                self.y().truncate().extend(0.0),
+                self.z().truncate().extend(0.0),
+                self.position().truncate().extend(1.0),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::InnerSpace;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn assert_vectors_near(a: cgmath::Vector4<f32>, b: cgmath::Vector4<f32>) {
+        assert!((a - b).magnitude() < EPSILON, "{a:?} != {b:?}");
+    }
+
+    // compares what the rotors do rather than their components, so `r` and `-r` are the same
+    fn assert_no_e2_rotors_near(a: NoE2Rotor, b: NoE2Rotor) {
+        assert_vectors_near(a.x(), b.x());
+        assert_vectors_near(a.y(), b.y());
+        assert_vectors_near(a.z(), b.z());
+        assert_vectors_near(a.w(), b.w());
+    }
+
+    fn assert_transforms_near(a: Transform, b: Transform) {
+        assert_vectors_near(a.position(), b.position());
+        assert_vectors_near(a.x(), b.x());
+        assert_vectors_near(a.y(), b.y());
+        assert_vectors_near(a.z(), b.z());
+        assert_vectors_near(a.w(), b.w());
+    }
+
+    fn no_e2_rotor_pairs() -> [(NoE2Rotor, NoE2Rotor); 4] {
+        [
+            (NoE2Rotor::identity(), NoE2Rotor::rotate_xz(1.0)),
+            (NoE2Rotor::rotate_xw(0.3), NoE2Rotor::rotate_zw(-2.0)),
+            (
+                NoE2Rotor::rotate_xz(0.5).then(NoE2Rotor::rotate_xw(1.2)),
+                NoE2Rotor::rotate_zw(0.7).then(NoE2Rotor::rotate_xz(-2.5)),
+            ),
+            // more than a half turn apart, so one of them has to be negated
+            (NoE2Rotor::rotate_xz(-3.0), NoE2Rotor::rotate_xz(3.0)),
+        ]
+    }
+
+    #[test]
+    fn no_e2_slerp_endpoints() {
+        for (a, b) in no_e2_rotor_pairs() {
+            assert_no_e2_rotors_near(a.slerp(b, 0.0), a);
+            assert_no_e2_rotors_near(a.slerp(b, 1.0), b);
+        }
+    }
+
+    #[test]
+    fn no_e2_slerp_midpoint_is_normalised() {
+        for (a, b) in no_e2_rotor_pairs() {
+            let mid = a.slerp(b, 0.5);
+            let norm =
+                (mid.s * mid.s + mid.e1e3 * mid.e1e3 + mid.e1e4 * mid.e1e4 + mid.e3e4 * mid.e3e4)
+                    .sqrt();
+            assert!((norm - 1.0).abs() < EPSILON, "norm of {norm}");
+        }
+
+        let mid = NoE2Rotor::identity().slerp(NoE2Rotor::rotate_xz(1.0), 0.5);
+        assert_no_e2_rotors_near(mid, NoE2Rotor::rotate_xz(0.5));
+    }
+
+    #[test]
+    fn inverse_undoes_transform() {
+        let rotors = [
+            Rotor::identity(),
+            Rotor::rotate_xy(0.4),
+            Rotor::rotate_yw(-1.3),
+            Rotor::rotate_xz(2.0).then(Rotor::rotate_zw(0.9)),
+            Rotor::rotate_xy(0.6)
+                .then(Rotor::rotate_yz(-0.2))
+                .then(Rotor::rotate_xw(3.0)),
+        ];
+        let offsets = [
+            cgmath::vec4(0.0, 0.0, 0.0, 0.0),
+            cgmath::vec4(1.0, 2.0, 3.0, 4.0),
+            cgmath::vec4(-5.0, 0.5, 0.0, -2.5),
+        ];
+        for rotor in rotors {
+            for offset in offsets {
+                let t = Transform::translation(offset).then(Transform::from_rotor(rotor));
+                assert_transforms_near(t.inverse().then(t), Transform::identity());
+                assert_transforms_near(t.then(t.inverse()), Transform::identity());
+            }
+        }
+    }
+
+    #[test]
+    fn matrix4_round_trip() {
+        use std::f32::consts::PI;
+
+        let rotors = [
+            // a positive trace
+            Rotor::identity(),
+            Rotor::rotate_xy(0.5),
+            Rotor::rotate_xz(-1.0).then(Rotor::rotate_yz(0.7)),
+            // half turns about x, y, and z, which each need a different branch
+            Rotor::rotate_yz(PI),
+            Rotor::rotate_xz(PI),
+            Rotor::rotate_xy(PI),
+            // close to half turns, with the trace just below zero
+            Rotor::rotate_yz(3.0).then(Rotor::rotate_xy(0.1)),
+            Rotor::rotate_xz(-3.0).then(Rotor::rotate_yz(0.1)),
+            Rotor::rotate_xy(3.0).then(Rotor::rotate_xz(-0.1)),
+        ];
+        let offsets = [
+            cgmath::vec4(0.0, 0.0, 0.0, 0.0),
+            cgmath::vec4(1.0, -2.0, 3.5, 0.0),
+        ];
+        for rotor in rotors {
+            for offset in offsets {
+                let t = Transform::translation(offset).then(Transform::from_rotor(rotor));
+                assert_transforms_near(Transform::from_matrix4(t.to_matrix4()), t);
+            }
+        }
     }
 }
@@ -0,0 +1,131 @@
+// 4d simplex noise, following Stefan Gustavson's "Simplex noise demystified"
+// the permutation table is replaced by hashing the lattice coordinates with the seed
+
+const F4: f32 = 0.309_017; // (sqrt(5) - 1) / 4
+const G4: f32 = 0.138_196_6; // (5 - sqrt(5)) / 20
+
+#[rustfmt::skip]
+const GRADIENTS: [[f32; 4]; 32] = [
+    [ 0.0,  1.0,  1.0,  1.0], [ 0.0,  1.0,  1.0, -1.0], [ 0.0,  1.0, -1.0,  1.0], [ 0.0,  1.0, -1.0, -1.0],
+    [ 0.0, -1.0,  1.0,  1.0], [ 0.0, -1.0,  1.0, -1.0], [ 0.0, -1.0, -1.0,  1.0], [ 0.0, -1.0, -1.0, -1.0],
+    [ 1.0,  0.0,  1.0,  1.0], [ 1.0,  0.0,  1.0, -1.0], [ 1.0,  0.0, -1.0,  1.0], [ 1.0,  0.0, -1.0, -1.0],
+    [-1.0,  0.0,  1.0,  1.0], [-1.0,  0.0,  1.0, -1.0], [-1.0,  0.0, -1.0,  1.0], [-1.0,  0.0, -1.0, -1.0],
+    [ 1.0,  1.0,  0.0,  1.0], [ 1.0,  1.0,  0.0, -1.0], [ 1.0, -1.0,  0.0,  1.0], [ 1.0, -1.0,  0.0, -1.0],
+    [-1.0,  1.0,  0.0,  1.0], [-1.0,  1.0,  0.0, -1.0], [-1.0, -1.0,  0.0,  1.0], [-1.0, -1.0,  0.0, -1.0],
+    [ 1.0,  1.0,  1.0,  0.0], [ 1.0,  1.0, -1.0,  0.0], [ 1.0, -1.0,  1.0,  0.0], [ 1.0, -1.0, -1.0,  0.0],
+    [-1.0,  1.0,  1.0,  0.0], [-1.0,  1.0, -1.0,  0.0], [-1.0, -1.0,  1.0,  0.0], [-1.0, -1.0, -1.0,  0.0],
+];
+
+/// 4d simplex noise averaging 0, the output is clamped to [-1, 1] but doesn't reach the ends of it,
+/// in practice it stays within about ±0.98
+pub fn simplex4(x: f32, y: f32, z: f32, w: f32, seed: u64) -> f32 {
+    let position = [x, y, z, w];
+
+    // skew the input space to find which cell of the simplex grid we are in
+    let skew = (x + y + z + w) * F4;
+    let cell = position.map(|p| (p + skew).floor());
+    let unskew = cell.iter().sum::<f32>() * G4;
+    let offset: [f32; 4] = std::array::from_fn(|i| position[i] - (cell[i] - unskew));
+
+    // rank the offset components to find which of the 24 simplices in the cell we are in
+    let mut rank = [0u8; 4];
+    for i in 0..4 {
+        for j in i + 1..4 {
+            if offset[i] > offset[j] {
+                rank[i] += 1;
+            } else {
+                rank[j] += 1;
+            }
+        }
+    }
+
+    let cell = cell.map(|c| c as i32);
+    let mut total = 0.0;
+    for corner in 0..5u8 {
+        // the corners are stepped to one axis at a time, largest offset component first
+        let step: [i32; 4] = rank.map(|rank| i32::from(rank + corner >= 4));
+        let corner_offset: [f32; 4] =
+            std::array::from_fn(|i| offset[i] - step[i] as f32 + G4 * f32::from(corner));
+
+        let falloff = 0.6 - corner_offset.iter().map(|o| o * o).sum::<f32>();
+        if falloff <= 0.0 {
+            continue;
+        }
+
+        let gradient = GRADIENTS[hash(std::array::from_fn(|i| cell[i] + step[i]), seed) % 32];
+        let dot = (0..4).map(|i| gradient[i] * corner_offset[i]).sum::<f32>();
+        total += falloff.powi(4) * dot;
+    }
+
+    (total * 27.0).clamp(-1.0, 1.0)
+}
+
+fn hash(coordinates: [i32; 4], seed: u64) -> usize {
+    let mut hash = seed;
+    for coordinate in coordinates {
+        hash ^= coordinate as u32 as u64;
+        hash = hash.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        hash ^= hash >> 32;
+    }
+    hash = hash.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    hash ^= hash >> 29;
+    hash as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::simplex4;
+    use rand::{Rng, SeedableRng, rngs::SmallRng};
+
+    fn random_points(count: usize) -> impl Iterator<Item = [f32; 4]> {
+        let mut rng = SmallRng::seed_from_u64(0);
+        std::iter::repeat_with(move || std::array::from_fn(|_| rng.random_range(-100.0..100.0)))
+            .take(count)
+    }
+
+    #[test]
+    fn output_is_finite_and_in_range() {
+        for seed in 0..4 {
+            for [x, y, z, w] in random_points(10_000) {
+                let value = simplex4(x, y, z, w, seed);
+                assert!(value.is_finite(), "simplex4({x}, {y}, {z}, {w}) = {value}");
+                assert!(
+                    (-1.0..=1.0).contains(&value),
+                    "simplex4({x}, {y}, {z}, {w}) = {value}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn gradient_is_bounded() {
+        const STEP: f32 = 1e-3;
+        for position in random_points(10_000) {
+            let value = simplex4(position[0], position[1], position[2], position[3], 0);
+            for axis in 0..4 {
+                let mut stepped = position;
+                stepped[axis] += STEP;
+                let [x, y, z, w] = stepped;
+                let slope = (simplex4(x, y, z, w, 0) - value).abs() / STEP;
+                assert!(
+                    slope < 10.0,
+                    "slope of {slope} along axis {axis} at {position:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn mean_is_near_zero() {
+        let mut sum = 0.0;
+        let mut count = 0;
+        for seed in 0..4 {
+            for [x, y, z, w] in random_points(10_000) {
+                sum += simplex4(x, y, z, w, seed) as f64;
+                count += 1;
+            }
+        }
+        let mean = sum / count as f64;
+        assert!(mean.abs() < 0.01, "mean of {mean}");
+    }
+}
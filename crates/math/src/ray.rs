@@ -0,0 +1,103 @@
+use crate::Aabb4;
+use cgmath::InnerSpace;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray4 {
+    pub origin: cgmath::Vector4<f32>,
+    pub direction: cgmath::Vector4<f32>,
+}
+
+impl Ray4 {
+    /// the direction is normalised, so distances along the ray are in world units,
+    /// it must not be zero since there is no direction to normalise it to
+    pub fn new(origin: cgmath::Vector4<f32>, direction: cgmath::Vector4<f32>) -> Self {
+        debug_assert!(
+            direction.magnitude2() > 0.0,
+            "the direction of a ray should not be zero"
+        );
+        Self {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    #[inline]
+    pub fn at(&self, t: f32) -> cgmath::Vector4<f32> {
+        self.origin + self.direction * t
+    }
+
+    /// the distances along the ray where it enters and exits the box, using the slab method,
+    /// the entry is negative when the origin is inside the box and `None` is returned when the box is behind the ray
+    pub fn intersect_aabb(&self, aabb: &Aabb4) -> Option<(f32, f32)> {
+        let mut entry = f32::NEG_INFINITY;
+        let mut exit = f32::INFINITY;
+        for i in 0..4 {
+            if self.direction[i] == 0.0 {
+                // parallel to both planes of this axis, so it is either always or never between them
+                if self.origin[i] < aabb.min[i] || self.origin[i] > aabb.max[i] {
+                    return None;
+                }
+                continue;
+            }
+
+            let t0 = (aabb.min[i] - self.origin[i]) / self.direction[i];
+            let t1 = (aabb.max[i] - self.origin[i]) / self.direction[i];
+            entry = entry.max(t0.min(t1));
+            exit = exit.min(t0.max(t1));
+        }
+        (entry <= exit && exit >= 0.0).then_some((entry, exit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Aabb4 {
+        Aabb4::new(
+            cgmath::vec4(0.0, 0.0, 0.0, 0.0),
+            cgmath::vec4(1.0, 1.0, 1.0, 1.0),
+        )
+    }
+
+    #[test]
+    fn axis_aligned_hit() {
+        let ray = Ray4::new(
+            cgmath::vec4(-2.0, 0.5, 0.5, 0.5),
+            cgmath::vec4(3.0, 0.0, 0.0, 0.0),
+        );
+        assert_eq!(ray.intersect_aabb(&unit_box()), Some((2.0, 3.0)));
+    }
+
+    #[test]
+    fn origin_inside_has_negative_entry() {
+        let ray = Ray4::new(
+            cgmath::vec4(0.25, 0.5, 0.5, 0.5),
+            cgmath::vec4(0.0, 0.0, 0.0, -1.0),
+        );
+        let (entry, exit) = ray
+            .intersect_aabb(&unit_box())
+            .expect("the ray should start inside the box");
+        assert!(entry < 0.0);
+        assert_eq!((entry, exit), (-0.5, 0.5));
+    }
+
+    #[test]
+    fn box_behind_is_missed() {
+        let ray = Ray4::new(
+            cgmath::vec4(2.0, 0.5, 0.5, 0.5),
+            cgmath::vec4(1.0, 0.0, 0.0, 0.0),
+        );
+        assert_eq!(ray.intersect_aabb(&unit_box()), None);
+    }
+
+    #[test]
+    fn parallel_outside_a_slab_is_missed() {
+        // moving along x towards the box, but always above it in z
+        let ray = Ray4::new(
+            cgmath::vec4(-2.0, 0.5, 1.5, 0.5),
+            cgmath::vec4(1.0, 0.0, 0.0, 0.0),
+        );
+        assert_eq!(ray.intersect_aabb(&unit_box()), None);
+    }
+}